@@ -0,0 +1,621 @@
+use crate::cli::{Args, GpuModel};
+use crate::fetcher::Fetcher;
+use crate::history::{self, PricePoint};
+use crate::notify::Notifier;
+use crate::scraper::{self, GpuListing, ProductVariant, StockStatus};
+use crate::storage::Storage;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinSet;
+
+/// Rules file (`--alerts`), e.g. in TOML:
+///
+/// ```toml
+/// [[rules]]
+/// name = "Cheap 5090"
+/// kind = "listing"
+/// models = ["5090"]
+/// status = ["in_stock"]
+/// max_price = 2100
+/// retailers = ["Best Buy", "Newegg"]
+///
+/// [[rules]]
+/// name = "5080 price drop"
+/// kind = "price_drop"
+/// models = ["5080"]
+/// stat = "median"
+/// percent = 10
+/// window = "24h"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSet {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    /// Models the rule applies to; empty means every model.
+    #[serde(default)]
+    pub models: Vec<GpuModel>,
+    #[serde(flatten)]
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Condition {
+    /// Fires for listings matching every given criterion.
+    Listing {
+        /// Accepted statuses; defaults to any available status.
+        #[serde(default)]
+        status: Vec<StockStatus>,
+        min_price: Option<f64>,
+        max_price: Option<f64>,
+        #[serde(default)]
+        retailers: Vec<String>,
+        #[serde(default)]
+        brands: Vec<String>,
+        #[serde(default)]
+        exclude_variants: Vec<ProductVariant>,
+    },
+    /// Fires when a price statistic of the model's available listings falls by
+    /// at least `percent` compared to its value `window` ago.
+    PriceDrop {
+        #[serde(default)]
+        stat: PriceStat,
+        percent: f64,
+        #[serde(deserialize_with = "deserialize_window")]
+        window: chrono::Duration,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceStat {
+    Min,
+    #[default]
+    Median,
+    Max,
+}
+
+impl PriceStat {
+    fn label(self) -> &'static str {
+        match self {
+            PriceStat::Min => "min",
+            PriceStat::Median => "median",
+            PriceStat::Max => "max",
+        }
+    }
+
+    fn of(self, point: &PricePoint) -> Option<f64> {
+        match self {
+            PriceStat::Min => point.min,
+            PriceStat::Median => point.median,
+            PriceStat::Max => point.max,
+        }
+    }
+}
+
+fn deserialize_window<'de, D: Deserializer<'de>>(deserializer: D) -> Result<chrono::Duration, D::Error> {
+    let window = String::deserialize(deserializer)?;
+    crate::cli::parse_since(&window).map_err(serde::de::Error::custom)
+}
+
+impl RuleSet {
+    /// Loads rules from a TOML, YAML or JSON file, picked by extension (TOML
+    /// by default).
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read alert rules {}", path.display()))?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let rules: RuleSet = match extension {
+            "yaml" | "yml" => serde_yaml::from_str(&text).map_err(anyhow::Error::from),
+            "json" => serde_json::from_str(&text).map_err(anyhow::Error::from),
+            _ => toml::from_str(&text).map_err(anyhow::Error::from),
+        }
+        .with_context(|| format!("Invalid alert rules in {}", path.display()))?;
+        rules.validate().with_context(|| format!("Invalid alert rules in {}", path.display()))?;
+        Ok(rules)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for rule in &self.rules {
+            match &rule.condition {
+                Condition::Listing { status, .. } => {
+                    // Unrecognised names deserialize as `Unknown` and would never match.
+                    if let Some(StockStatus::Unknown(name)) =
+                        status.iter().find(|s| matches!(s, StockStatus::Unknown(_)))
+                    {
+                        bail!(
+                            "Rule \"{}\": unknown status \"{}\" (expected in_stock, preorder, notify_me, \
+                             marketplace_available, out_of_stock or not_tracking)",
+                            rule.name,
+                            name
+                        );
+                    }
+                }
+                Condition::PriceDrop { percent, window, .. } => {
+                    if *percent <= 0.0 || *window <= chrono::Duration::zero() {
+                        bail!("Rule \"{}\": percent and window must be greater than zero", rule.name);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The longest price-drop window, i.e. how much price history to keep.
+    fn longest_window(&self) -> Option<chrono::Duration> {
+        self.rules
+            .iter()
+            .filter_map(|rule| match rule.condition {
+                Condition::PriceDrop { window, .. } => Some(window),
+                Condition::Listing { .. } => None,
+            })
+            .max()
+    }
+}
+
+impl Rule {
+    fn applies_to(&self, model: GpuModel) -> bool {
+        self.models.is_empty() || self.models.contains(&model)
+    }
+}
+
+/// Details of a fired price-drop rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceDrop {
+    pub stat: PriceStat,
+    pub from: f64,
+    pub to: f64,
+    pub percent: f64,
+    /// When the `from` price was seen.
+    pub since: DateTime<Utc>,
+}
+
+/// A rule that fired, with the listings that made it fire.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertEvent {
+    pub rule: String,
+    pub model: GpuModel,
+    pub fired_at: DateTime<Utc>,
+    pub message: String,
+    /// Newly matching listings (listing rules) or the cheapest available
+    /// listing (price-drop rules).
+    pub listings: Vec<GpuListing>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_drop: Option<PriceDrop>,
+}
+
+/// What the engine remembers between evaluations, so rules fire when they
+/// start matching rather than on every refresh.
+#[derive(Default)]
+struct EngineState {
+    /// Identities each listing rule matched last time, per model.
+    matched: HashMap<(usize, GpuModel), HashSet<String>>,
+    /// Whether each price-drop rule was triggered last time, per model.
+    dropped: HashMap<(usize, GpuModel), bool>,
+    /// Recent price statistics per model, oldest first.
+    prices: HashMap<GpuModel, VecDeque<PricePoint>>,
+}
+
+/// Evaluates alert rules against each freshly parsed page of listings and
+/// passes fired alerts to the notifiers. Cloning is cheap and clones share
+/// state; the default engine has no rules.
+#[derive(Clone, Default)]
+pub struct AlertEngine {
+    rules: Arc<RuleSet>,
+    state: Arc<Mutex<EngineState>>,
+    notifier: Option<Notifier>,
+    /// Notifications being sent in the background.
+    deliveries: Arc<Mutex<JoinSet<()>>>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: RuleSet, notifier: Option<Notifier>) -> Self {
+        AlertEngine { rules: Arc::new(rules), state: Arc::default(), notifier, deliveries: Arc::default() }
+    }
+
+    /// Loads `--alerts` and restores rule state from the history database.
+    /// Notifications go through `fetcher`.
+    pub fn from_args(args: &Args, fetcher: &Fetcher, storage: &Storage) -> Result<Self> {
+        let notifier = Some(Notifier::from_args(args, fetcher)?).filter(|n| !n.is_empty());
+        let Some(path) = &args.alerts else {
+            if notifier.is_some() {
+                eprintln!("Warning: notifiers are configured but no --alerts rules are; nothing will be sent.");
+            }
+            return Ok(AlertEngine::default());
+        };
+        let engine = AlertEngine::new(RuleSet::load(path)?, notifier);
+        engine.restore(storage)?;
+        Ok(engine)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.rules.is_empty()
+    }
+
+    /// Picks up where the last recorded scrape of each model left off: price
+    /// history is seeded so price-drop rules work from the first check, and
+    /// the latest scrape is evaluated without notifying. One-shot runs (e.g.
+    /// from cron) then only fire for what changed since the previous scrape.
+    fn restore(&self, storage: &Storage) -> Result<()> {
+        let mut latest = Vec::new();
+        for model in self.models_with(|_| true) {
+            if let Some((scraped_at, listings)) = storage.latest_scrape(model)? {
+                latest.push((model, scraped_at, listings));
+            }
+        }
+        if let Some(window) = self.rules.longest_window() {
+            let mut by_model: HashMap<GpuModel, Vec<history::Observation>> = HashMap::new();
+            for observation in storage.observations(None, Some(Utc::now() - window))? {
+                by_model.entry(observation.model).or_default().push(observation);
+            }
+            let mut state = self.state.lock().expect("alert state lock poisoned");
+            for model in self.models_with(|rule| matches!(rule.condition, Condition::PriceDrop { .. })) {
+                let mut observations = by_model.remove(&model).unwrap_or_default();
+                // The latest scrape adds its own price point when it's replayed below.
+                if let Some((_, scraped_at, _)) = latest.iter().find(|(m, ..)| *m == model) {
+                    observations.retain(|o| o.scraped_at != *scraped_at);
+                }
+                let history = history::model_history(model, &observations, None);
+                state.prices.insert(model, history.prices.into());
+            }
+        }
+        for (model, scraped_at, listings) in latest {
+            self.evaluate(model, &listings, scraped_at.with_timezone(&Local));
+        }
+        Ok(())
+    }
+
+    /// Models that at least one rule matching `filter` applies to.
+    fn models_with(&self, filter: impl Fn(&Rule) -> bool) -> HashSet<GpuModel> {
+        use clap::ValueEnum;
+        GpuModel::value_variants()
+            .iter()
+            .copied()
+            .filter(|model| self.rules.rules.iter().any(|rule| rule.applies_to(*model) && filter(rule)))
+            .collect()
+    }
+
+    /// Evaluates the rules for a freshly parsed page and starts sending any
+    /// fired alerts to the notifiers in the background, so the check (or page
+    /// load) doesn't wait on them. Delivery failures are reported on stderr
+    /// rather than failing the check.
+    pub fn check(&self, model: GpuModel, listings: &[GpuListing], fetched_at: DateTime<Local>) -> Vec<AlertEvent> {
+        let events = self.evaluate(model, listings, fetched_at);
+        if let Some(notifier) = self.notifier.clone().filter(|_| !events.is_empty()) {
+            let fired = events.clone();
+            let mut deliveries = self.deliveries.lock().expect("delivery lock poisoned");
+            // Reap finished deliveries so a long-running server doesn't collect them.
+            while deliveries.try_join_next().is_some() {}
+            deliveries.spawn(async move {
+                if let Err(e) = notifier.send(&fired).await {
+                    eprintln!("Failed to send alert notifications: {:#}", e);
+                }
+            });
+        }
+        events
+    }
+
+    /// Waits for notifications still being sent, e.g. before a CLI run exits.
+    pub async fn flush(&self) {
+        let mut deliveries = std::mem::take(&mut *self.deliveries.lock().expect("delivery lock poisoned"));
+        while deliveries.join_next().await.is_some() {}
+    }
+
+    /// Evaluates every rule for `model` against a freshly parsed page and
+    /// returns the rules that started matching.
+    pub fn evaluate(&self, model: GpuModel, listings: &[GpuListing], fetched_at: DateTime<Local>) -> Vec<AlertEvent> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let now: DateTime<Utc> = fetched_at.into();
+        let mut state = self.state.lock().expect("alert state lock poisoned");
+        let point = history::price_point(now, listings);
+        if let Some(window) = self.rules.longest_window() {
+            let prices = state.prices.entry(model).or_default();
+            while prices.front().is_some_and(|p| p.time < now - window) {
+                prices.pop_front();
+            }
+        }
+
+        let mut events = Vec::new();
+        for (index, rule) in self.rules.rules.iter().enumerate() {
+            if !rule.applies_to(model) {
+                continue;
+            }
+            match &rule.condition {
+                Condition::Listing { .. } => {
+                    let matching: Vec<&GpuListing> =
+                        listings.iter().filter(|l| rule.condition.matches_listing(l)).collect();
+                    let previous = state.matched.entry((index, model)).or_default();
+                    let new: Vec<GpuListing> = matching
+                        .iter()
+                        .filter(|l| !previous.contains(l.identity()))
+                        .map(|l| (*l).clone())
+                        .collect();
+                    *previous = matching.iter().map(|l| l.identity().to_string()).collect();
+                    if !new.is_empty() {
+                        events.push(AlertEvent {
+                            rule: rule.name.clone(),
+                            model,
+                            fired_at: now,
+                            message: format!(
+                                "{} new matching listing{} for {}",
+                                new.len(),
+                                if new.len() == 1 { "" } else { "s" },
+                                model.cli_name()
+                            ),
+                            listings: new,
+                            price_drop: None,
+                        });
+                    }
+                }
+                Condition::PriceDrop { stat, percent, window } => {
+                    let reference = state.prices.get(&model).and_then(|prices| {
+                        prices
+                            .iter()
+                            .filter(|p| p.time >= now - *window && p.time < now)
+                            .find_map(|p| stat.of(p).map(|price| (p.time, price)))
+                    });
+                    let drop = match (reference, stat.of(&point)) {
+                        (Some((since, from)), Some(to)) if from > 0.0 => {
+                            let fell = (from - to) / from * 100.0;
+                            (fell >= *percent).then_some(PriceDrop { stat: *stat, from, to, percent: fell, since })
+                        }
+                        _ => None,
+                    };
+                    let was_triggered = state.dropped.insert((index, model), drop.is_some()).unwrap_or(false);
+                    if let Some(drop) = drop.filter(|_| !was_triggered) {
+                        events.push(AlertEvent {
+                            rule: rule.name.clone(),
+                            model,
+                            fired_at: now,
+                            message: format!(
+                                "{} {} price fell {:.1}% since {}: ${:.2} → ${:.2}",
+                                model.cli_name(),
+                                drop.stat.label(),
+                                drop.percent,
+                                drop.since.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                                drop.from,
+                                drop.to
+                            ),
+                            listings: cheapest_available(listings).into_iter().cloned().collect(),
+                            price_drop: Some(drop),
+                        });
+                    }
+                }
+            }
+        }
+        if self.rules.longest_window().is_some() {
+            state.prices.entry(model).or_default().push_back(point);
+        }
+        events
+    }
+}
+
+/// The cheapest available listing that has a price.
+pub fn cheapest_available(listings: &[GpuListing]) -> Option<&GpuListing> {
+    listings
+        .iter()
+        .filter(|l| l.status.is_available())
+        .filter_map(|l| l.price_numeric.map(|price| (price, l)))
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, l)| l)
+}
+
+impl Condition {
+    fn matches_listing(&self, listing: &GpuListing) -> bool {
+        let Condition::Listing { status, min_price, max_price, retailers, brands, exclude_variants } = self else {
+            return false;
+        };
+        let status_ok = if status.is_empty() {
+            listing.status.is_available()
+        } else {
+            status.contains(&listing.status)
+        };
+        let price_ok = match (min_price, max_price) {
+            (None, None) => true,
+            _ => listing.price_numeric.is_some_and(|price| {
+                min_price.is_none_or(|min| price >= min) && max_price.is_none_or(|max| price <= max)
+            }),
+        };
+        let retailer_ok = retailers.is_empty()
+            || listing.retailer.as_deref().is_some_and(|r| {
+                retailers.iter().any(|wanted| scraper::name_key(wanted) == scraper::name_key(r))
+            });
+        let brand_ok = brands.is_empty()
+            || listing.product.as_ref().and_then(|p| p.brand.as_deref()).is_some_and(|b| {
+                brands.iter().any(|wanted| scraper::name_key(wanted) == scraper::name_key(b))
+            });
+        let variant_ok = !listing
+            .product
+            .as_ref()
+            .is_some_and(|p| exclude_variants.iter().any(|v| p.has_variant(*v)));
+        status_ok && price_ok && retailer_ok && brand_ok && variant_ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::path::PathBuf;
+
+    fn at(hours: i64) -> DateTime<Local> {
+        (Utc.with_ymd_and_hms(2026, 6, 15, 12, 0, 0).unwrap() + chrono::Duration::hours(hours)).with_timezone(&Local)
+    }
+
+    fn engine(rules: &str) -> AlertEngine {
+        let rules: RuleSet = toml::from_str(rules).unwrap();
+        rules.validate().unwrap();
+        AlertEngine::new(rules, None)
+    }
+
+    /// Writes `contents` to a per-test file in the temp directory and loads it.
+    fn load(name: &str, contents: &str) -> Result<RuleSet> {
+        let path: PathBuf = std::env::temp_dir().join(format!("gpu_pricecheck-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        let rules = RuleSet::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        rules
+    }
+
+    const CHEAP_5090: &str = r#"
+        [[rules]]
+        name = "Cheap 5090"
+        kind = "listing"
+        models = ["5090"]
+        max_price = 2100
+    "#;
+
+    const PRICE_DROP: &str = r#"
+        [[rules]]
+        name = "5090 drop"
+        kind = "price_drop"
+        models = ["5090"]
+        stat = "min"
+        percent = 10
+        window = "24h"
+    "#;
+
+    #[test]
+    fn load_reads_each_format() {
+        let toml = load("rules.toml", CHEAP_5090).unwrap();
+        assert_eq!(toml.rules.len(), 1);
+        assert_eq!(toml.rules[0].models, vec![GpuModel::Rtx5090]);
+        let json = load(
+            "rules.json",
+            r#"{"rules": [{"name": "Drop", "kind": "price_drop", "percent": 5, "window": "12h"}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(json.rules[0].condition, Condition::PriceDrop { stat: PriceStat::Median, .. }));
+    }
+
+    #[test]
+    fn load_rejects_unknown_keys() {
+        let typo = CHEAP_5090.replace("max_price", "max_prize");
+        let error = format!("{:#}", load("typo.toml", &typo).unwrap_err());
+        assert!(error.contains("Invalid alert rules"), "{}", error);
+        assert!(error.contains("max_prize"), "{}", error);
+    }
+
+    #[test]
+    fn load_rejects_unknown_statuses() {
+        let rules = format!("{}status = [\"in_stok\"]\n", CHEAP_5090);
+        let error = format!("{:#}", load("status.toml", &rules).unwrap_err());
+        assert!(error.contains("Rule \"Cheap 5090\": unknown status \"in_stok\""), "{}", error);
+    }
+
+    #[test]
+    fn load_rejects_non_positive_drops() {
+        let rules = PRICE_DROP.replace("percent = 10", "percent = 0");
+        let error = format!("{:#}", load("drop.toml", &rules).unwrap_err());
+        assert!(error.contains("percent and window must be greater than zero"), "{}", error);
+    }
+
+    #[test]
+    fn listing_rule_fires_for_newly_matching_listings() {
+        let engine = engine(CHEAP_5090);
+        let a = GpuListing::for_test("a", StockStatus::InStock, Some(1999.0));
+        let b = GpuListing::for_test("b", StockStatus::InStock, Some(2050.0));
+        let pricey = GpuListing::for_test("c", StockStatus::InStock, Some(2500.0));
+
+        let events = engine.evaluate(GpuModel::Rtx5090, &[a.clone(), pricey.clone()], at(0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].message, "1 new matching listing for 5090");
+        assert_eq!(events[0].listings[0].link, a.link);
+
+        // Still matching: nothing new to report.
+        assert!(engine.evaluate(GpuModel::Rtx5090, &[a.clone(), pricey.clone()], at(1)).is_empty());
+
+        let events = engine.evaluate(GpuModel::Rtx5090, &[a.clone(), b.clone()], at(2));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].listings.len(), 1);
+        assert_eq!(events[0].listings[0].link, b.link);
+
+        // A listing that stops matching and comes back fires again.
+        let sold_out = GpuListing { status: StockStatus::OutOfStock, ..a.clone() };
+        assert!(engine.evaluate(GpuModel::Rtx5090, &[sold_out, b.clone()], at(3)).is_empty());
+        assert_eq!(engine.evaluate(GpuModel::Rtx5090, &[a, b], at(4)).len(), 1);
+    }
+
+    #[test]
+    fn listing_rule_ignores_other_models() {
+        let engine = engine(CHEAP_5090);
+        let a = GpuListing::for_test("a", StockStatus::InStock, Some(1999.0));
+        assert!(engine.evaluate(GpuModel::Rtx5080, &[a], at(0)).is_empty());
+    }
+
+    #[test]
+    fn price_drop_fires_once_and_rearms() {
+        let engine = engine(PRICE_DROP);
+        let priced = |price| vec![GpuListing::for_test("a", StockStatus::InStock, Some(price))];
+        let check = |price, hours| engine.evaluate(GpuModel::Rtx5090, &priced(price), at(hours));
+
+        assert!(check(1000.0, 0).is_empty());
+        // 5% below the oldest price in the window: under the threshold.
+        assert!(check(950.0, 1).is_empty());
+        let events = check(880.0, 2);
+        assert_eq!(events.len(), 1);
+        let drop = events[0].price_drop.as_ref().unwrap();
+        assert_eq!((drop.from, drop.to), (1000.0, 880.0));
+        assert!((drop.percent - 12.0).abs() < 1e-9);
+        assert_eq!(drop.since, at(0));
+        assert_eq!(events[0].listings.len(), 1);
+
+        // Still down: doesn't fire again until the price recovers.
+        assert!(check(870.0, 3).is_empty());
+        assert!(check(1000.0, 4).is_empty());
+        assert_eq!(check(890.0, 5).len(), 1);
+    }
+
+    #[test]
+    fn price_drop_only_compares_within_the_window() {
+        let engine = engine(PRICE_DROP);
+        let priced = |price| vec![GpuListing::for_test("a", StockStatus::InStock, Some(price))];
+        assert!(engine.evaluate(GpuModel::Rtx5090, &priced(1000.0), at(0)).is_empty());
+        assert!(engine.evaluate(GpuModel::Rtx5090, &priced(960.0), at(20)).is_empty());
+        // 1000 was seen 30h ago, outside the window; 960 → 900 is only 6.25%.
+        assert!(engine.evaluate(GpuModel::Rtx5090, &priced(900.0), at(30)).is_empty());
+    }
+
+    #[test]
+    fn restore_does_not_refire_the_latest_scrape() {
+        let storage = Storage::new(PathBuf::from(":memory:"));
+        let a = GpuListing::for_test("a", StockStatus::InStock, Some(1999.0));
+        let b = GpuListing::for_test("b", StockStatus::InStock, Some(2050.0));
+        let now = Local::now();
+        let hours_ago = |hours| now - chrono::Duration::hours(hours);
+        let pricier = GpuListing { price_numeric: Some(2400.0), ..a.clone() };
+        storage.record(GpuModel::Rtx5090, &[pricier], hours_ago(2).into()).unwrap();
+        storage.record(GpuModel::Rtx5090, std::slice::from_ref(&a), hours_ago(1).into()).unwrap();
+
+        let engine = engine(&format!("{}{}", CHEAP_5090, PRICE_DROP));
+        engine.restore(&storage).unwrap();
+
+        // The drop (2400 → 1999) and `a` were already seen by the last recorded scrape.
+        assert!(engine.evaluate(GpuModel::Rtx5090, std::slice::from_ref(&a), now).is_empty());
+        let events = engine.evaluate(GpuModel::Rtx5090, &[a, b.clone()], now + chrono::Duration::minutes(5));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].rule, "Cheap 5090");
+        assert_eq!(events[0].listings[0].link, b.link);
+    }
+
+    #[test]
+    fn restore_without_history_starts_fresh() {
+        let storage = Storage::new(PathBuf::from(":memory:"));
+        let engine = engine(CHEAP_5090);
+        engine.restore(&storage).unwrap();
+        let a = GpuListing::for_test("a", StockStatus::InStock, Some(1999.0));
+        assert_eq!(engine.evaluate(GpuModel::Rtx5090, &[a], at(0)).len(), 1);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::fetcher::FetchedPage;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A cached page and when it was stored.
+struct CacheEntry {
+    page: FetchedPage,
+    stored_at: Instant,
+}
+
+type Slot = Arc<tokio::sync::Mutex<Option<CacheEntry>>>;
+
+/// In-memory, per-URL page cache with a fixed TTL.
+///
+/// Each URL has its own async lock, held while a miss is being fetched, so
+/// concurrent requests for the same URL wait for the first fetch instead of
+/// all hitting the upstream site (single-flight). Failed fetches aren't cached.
+pub struct PageCache {
+    ttl: Duration,
+    slots: Mutex<HashMap<String, Slot>>,
+}
+
+impl PageCache {
+    /// A TTL of zero disables caching.
+    pub fn new(ttl: Duration) -> Self {
+        PageCache { ttl, slots: Mutex::new(HashMap::new()) }
+    }
+
+    fn slot(&self, key: &str) -> Slot {
+        let mut slots = self.slots.lock().expect("page cache lock poisoned");
+        slots.entry(key.to_string()).or_default().clone()
+    }
+
+    /// Returns the cached page for `key` if it is younger than the TTL,
+    /// otherwise runs `fetch` and caches its result. The flag is true when
+    /// the page was freshly fetched by this call.
+    pub async fn get_or_fetch<F, Fut>(&self, key: &str, fetch: F) -> Result<(FetchedPage, bool)>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<FetchedPage>>,
+    {
+        if self.ttl.is_zero() {
+            return Ok((fetch().await?, true));
+        }
+        let slot = self.slot(key);
+        let mut entry = slot.lock().await;
+        if let Some(cached) = entry.as_ref() {
+            if cached.stored_at.elapsed() < self.ttl {
+                return Ok((cached.page.clone(), false));
+            }
+        }
+        let page = fetch().await?;
+        *entry = Some(CacheEntry { page: page.clone(), stored_at: Instant::now() });
+        Ok((page, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const URL: &str = "https://www.nowinstock.net/computers/videocards/nvidia/rtx5090/";
+
+    /// A fetch that counts its calls and takes `delay` to finish.
+    async fn fetch(calls: &AtomicUsize, delay: Duration) -> Result<FetchedPage> {
+        let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        tokio::time::sleep(delay).await;
+        Ok(FetchedPage { body: format!("page {}", call), attempts: 1, fetched_at: chrono::Local::now() })
+    }
+
+    #[tokio::test]
+    async fn concurrent_callers_share_one_fetch() {
+        let cache = PageCache::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        let results = futures::future::join_all(
+            (0..5).map(|_| cache.get_or_fetch(URL, || fetch(&calls, Duration::from_millis(50)))),
+        )
+        .await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let results: Vec<(FetchedPage, bool)> = results.into_iter().map(Result::unwrap).collect();
+        assert!(results.iter().all(|(page, _)| page.body == "page 1"));
+        assert_eq!(results.iter().filter(|(_, fresh)| *fresh).count(), 1);
+
+        // Other URLs have their own entries.
+        let (page, fresh) = cache.get_or_fetch("other", || fetch(&calls, Duration::ZERO)).await.unwrap();
+        assert_eq!((page.body.as_str(), fresh), ("page 2", true));
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_refetched() {
+        let cache = PageCache::new(Duration::from_millis(50));
+        let calls = AtomicUsize::new(0);
+        let (_, fresh) = cache.get_or_fetch(URL, || fetch(&calls, Duration::ZERO)).await.unwrap();
+        assert!(fresh);
+        let (page, fresh) = cache.get_or_fetch(URL, || fetch(&calls, Duration::ZERO)).await.unwrap();
+        assert_eq!((page.body.as_str(), fresh), ("page 1", false));
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        let (page, fresh) = cache.get_or_fetch(URL, || fetch(&calls, Duration::ZERO)).await.unwrap();
+        assert_eq!((page.body.as_str(), fresh), ("page 2", true));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_bypasses_the_cache() {
+        let cache = PageCache::new(Duration::ZERO);
+        let calls = AtomicUsize::new(0);
+        for expected in ["page 1", "page 2", "page 3"] {
+            let (page, fresh) = cache.get_or_fetch(URL, || fetch(&calls, Duration::ZERO)).await.unwrap();
+            assert_eq!((page.body.as_str(), fresh), (expected, true));
+        }
+        assert!(cache.slots.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_fetches_are_not_cached() {
+        let cache = PageCache::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        let failed = cache.get_or_fetch(URL, || async { Err(anyhow::anyhow!("upstream down")) }).await;
+        assert_eq!(failed.unwrap_err().to_string(), "upstream down");
+        let (page, fresh) = cache.get_or_fetch(URL, || fetch(&calls, Duration::ZERO)).await.unwrap();
+        assert_eq!((page.body.as_str(), fresh), ("page 1", true));
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Metadata saved next to each recorded response body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub url: String,
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    /// RFC 3339 timestamp of when the response was received.
+    pub recorded_at: String,
+    /// Body file name, relative to the cassette directory.
+    pub body_file: String,
+}
+
+/// A directory of recorded HTTP responses keyed by URL.
+///
+/// Each response is stored as `<key>.html` (the body) and `<key>.json`
+/// (a `RecordedResponse`), where the key is derived from the URL. Recording
+/// the same URL again overwrites the previous entry.
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    dir: PathBuf,
+}
+
+impl Cassette {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Cassette { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File name stem for a URL, e.g.
+    /// "https://www.nowinstock.net/computers/videocards/nvidia/rtx5090/"
+    /// -> "www.nowinstock.net_computers_videocards_nvidia_rtx5090".
+    fn key(url: &str) -> String {
+        let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+        let key: String = without_scheme
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+            .collect();
+        key.trim_matches('_').to_string()
+    }
+
+    pub async fn record(&self, url: &str, status: u16, headers: &HeaderMap, body: &str) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create cassette directory {}", self.dir.display()))?;
+        let key = Self::key(url);
+        let body_file = format!("{}.html", key);
+        let meta = RecordedResponse {
+            url: url.to_string(),
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| {
+                    (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned())
+                })
+                .collect(),
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            body_file: body_file.clone(),
+        };
+        let meta_json = serde_json::to_string_pretty(&meta)
+            .context("Failed to serialize recorded response metadata")?;
+        tokio::fs::write(self.dir.join(&body_file), body)
+            .await
+            .with_context(|| format!("Failed to write recorded body for {}", url))?;
+        tokio::fs::write(self.dir.join(format!("{}.json", key)), meta_json)
+            .await
+            .with_context(|| format!("Failed to write recorded metadata for {}", url))?;
+        Ok(())
+    }
+
+    /// Looks up the recorded response for `url`, returning its metadata and body.
+    pub async fn replay(&self, url: &str) -> Result<(RecordedResponse, String)> {
+        let key = Self::key(url);
+        let meta_path = self.dir.join(format!("{}.json", key));
+        let meta_json = tokio::fs::read_to_string(&meta_path).await.with_context(|| {
+            format!("No recorded response for {} (expected {})", url, meta_path.display())
+        })?;
+        let meta: RecordedResponse = serde_json::from_str(&meta_json)
+            .with_context(|| format!("Invalid cassette metadata in {}", meta_path.display()))?;
+        let body = tokio::fs::read_to_string(self.dir.join(&meta.body_file))
+            .await
+            .with_context(|| format!("Failed to read recorded body for {}", url))?;
+        Ok((meta, body))
+    }
+}
@@ -1,23 +1,44 @@
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use crate::scraper::{ProductVariant, USER_AGENT};
 use std::fmt;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
 use thiserror::Error;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum, Serialize, Deserialize)]
 pub enum GpuModel {
     #[value(name = "5090")]
+    #[serde(rename = "5090")]
     Rtx5090,
     #[value(name = "5080")]
+    #[serde(rename = "5080")]
     Rtx5080,
     #[value(name = "5070ti")]
+    #[serde(rename = "5070ti")]
     Rtx5070Ti,
     #[value(name = "5070")]
+    #[serde(rename = "5070")]
     Rtx5070,
     #[value(name = "9070xt")]
+    #[serde(rename = "9070xt")]
     Rx9070Xt,
 }
 
+impl GpuModel {
+    /// Short name used on the command line and in file names (e.g. "5090").
+    pub fn cli_name(&self) -> &'static str {
+        match self {
+            GpuModel::Rtx5090 => "5090",
+            GpuModel::Rtx5080 => "5080",
+            GpuModel::Rtx5070Ti => "5070ti",
+            GpuModel::Rtx5070 => "5070",
+            GpuModel::Rx9070Xt => "9070xt",
+        }
+    }
+}
+
 // Implement Display to get the URL part
 impl fmt::Display for GpuModel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -52,6 +73,7 @@ impl FromStr for GpuModel {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum SortColumn {
     Name,
+    Retailer,
     Status,
     Price,
     #[value(name="last")]
@@ -65,6 +87,7 @@ impl std::str::FromStr for SortColumn {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "name" => Ok(SortColumn::Name),
+            "retailer" | "store" => Ok(SortColumn::Retailer),
             "status" => Ok(SortColumn::Status),
             "price" => Ok(SortColumn::Price),
             "last" | "lastavailable" | "last_available" => Ok(SortColumn::LastAvailable),
@@ -75,12 +98,66 @@ impl std::str::FromStr for SortColumn {
 }
 
 
+/// Columns the listings table can show (`--columns`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TableColumn {
+    Model,
+    Retailer,
+    Name,
+    Brand,
+    Status,
+    Price,
+    #[value(name = "last")]
+    LastAvailable,
+    Link,
+}
+
+/// How links are shown in the listings table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LinkStyle {
+    /// Shortened on terminals narrower than 160 columns (and with --compact), full otherwise
+    Auto,
+    Full,
+    /// Host and the start of the path, e.g. "bestbuy.com/site/nvidia-geforce-r…"
+    Short,
+    Hide,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum OutputFormat {
     Table,
     Json,
     Yaml,
     Toml,
+    Csv,
+    Tsv,
+    /// Newline-delimited JSON: one compact object per line
+    Ndjson,
+    /// GitHub-flavored Markdown table
+    Markdown,
+    /// Standalone HTML page with inlined styles
+    Html,
+}
+
+/// How the SMTP connection is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS (usually port 587)
+    Starttls,
+    /// TLS from the start (usually port 465)
+    Tls,
+    /// No encryption; only for local relays and testing
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn default_port(&self) -> u16 {
+        match self {
+            SmtpSecurity::Starttls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
 }
 
 // Helper function to parse SocketAddr
@@ -97,9 +174,124 @@ fn parse_socket_addr(s: &str) -> Result<SocketAddr, String> {
     Err(format!("Invalid socket address or port: {}", s))
 }
 
+// Helper function to parse a look-back window such as 30m, 12h, 7d or 2w
+pub fn parse_since(s: &str) -> Result<chrono::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid duration: {} (expected e.g. 30m, 12h, 7d)", s))?;
+    let window = match unit {
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" | "" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        _ => return Err(format!("Invalid duration unit in {} (use m, h, d or w)", s)),
+    };
+    // The window is subtracted from the current time, so that must fit too.
+    window
+        .filter(|&window| chrono::Utc::now().checked_sub_signed(window).is_some())
+        .ok_or_else(|| format!("Invalid duration: {} (duration out of range)", s))
+}
+
+// Helper function to parse a re-check interval such as 45, 30s, 5m or 1h
+fn parse_interval(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid interval: {} (expected e.g. 45, 30s, 5m, 1h)", s))?;
+    if amount == 0 {
+        return Err("Interval must be greater than zero".to_string());
+    }
+    let secs = match unit {
+        "s" | "" => Some(amount),
+        "m" => amount.checked_mul(60),
+        "h" => amount.checked_mul(3600),
+        _ => return Err(format!("Invalid interval unit in {} (use s, m or h)", s)),
+    };
+    let secs = secs.ok_or_else(|| format!("Invalid interval: {} (interval out of range)", s))?;
+    Ok(std::time::Duration::from_secs(secs))
+}
+
+// Helper function to parse a local time of day such as 08:00
+fn parse_time_of_day(s: &str) -> Result<chrono::NaiveTime, String> {
+    chrono::NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time of day: {} (expected HH:MM, e.g. 08:00)", s))
+}
+
+// Helper function to parse a "Name: value" header
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Expected \"Name: value\", got: {}", s))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+// Helper function to parse a MODEL=SECS pair
+fn parse_model_interval(s: &str) -> Result<(GpuModel, u64), String> {
+    let (model, secs) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Expected MODEL=SECS, got: {}", s))?;
+    let model = model.trim().parse::<GpuModel>().map_err(|e| e.to_string())?;
+    let secs = secs
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("Invalid interval in {}: expected seconds", s))?;
+    if secs == 0 {
+        return Err(format!("Interval for {} must be greater than zero", model.cli_name()));
+    }
+    Ok((model, secs))
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Show status and price changes per listing recorded in the history database
+    History(HistoryArgs),
+    /// Send a sample alert through the configured notifiers
+    TestNotify,
+    /// Send the digest email now (e.g. from cron) instead of waiting for --email-digest-at
+    EmailDigest(EmailDigestArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct EmailDigestArgs {
+    /// How far back price changes are summarized
+    #[arg(long, value_parser = parse_since, default_value = "24h")]
+    pub since: chrono::Duration,
+
+    /// Print the plain-text digest instead of sending it
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct HistoryArgs {
+    /// GPU Model to show history for
+    #[arg(value_enum, default_value = "5080")]
+    pub gpu: GpuModel,
+
+    /// Only show changes within this window (e.g. 30m, 12h, 7d, 2w)
+    #[arg(long, value_parser = parse_since)]
+    pub since: Option<chrono::Duration>,
+
+    /// Only show listings from these retailers (comma-separated or repeated)
+    #[arg(long, value_delimiter = ',')]
+    pub retailer: Vec<String>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Checks GPU stock and prices from nowinstock.net", long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// GPU Model to check stock for (ignored if --cheapest-each or --web is used)
     #[arg(value_enum, default_value = "5080")]
     pub gpu: GpuModel,
@@ -116,6 +308,22 @@ pub struct Args {
     #[arg(long)]
     pub all: bool,
 
+    /// Only show listings from these retailers (comma-separated or repeated, e.g. "Best Buy,Newegg")
+    #[arg(long, value_delimiter = ',')]
+    pub retailer: Vec<String>,
+
+    /// Hide listings from these retailers (comma-separated or repeated)
+    #[arg(long, value_delimiter = ',')]
+    pub exclude_retailer: Vec<String>,
+
+    /// Only show cards from these board partners (comma-separated or repeated, e.g. "ASUS,MSI")
+    #[arg(long, value_delimiter = ',')]
+    pub brand: Vec<String>,
+
+    /// Hide cards with these variant flags (comma-separated or repeated)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub exclude_variant: Vec<ProductVariant>,
+
     /// Limit the number of results shown (used by CLI, potentially web in future)
     #[arg(short = 'n', long, value_parser = clap::value_parser!(usize))]
     pub limit: Option<usize>,
@@ -128,6 +336,43 @@ pub struct Args {
     #[arg(short = 'c', long)]
     pub cheapest_each: bool,
 
+    /// Only show what changed since the previous run of this model: new and removed
+    /// listings, status transitions and price moves
+    #[arg(long, conflicts_with = "cheapest_each")]
+    pub diff: bool,
+
+    /// Re-run the check on a schedule (e.g. 30s, 5m), redrawing the table and
+    /// highlighting rows that changed; stop with Ctrl-C
+    #[arg(long, value_name = "INTERVAL", value_parser = parse_interval, conflicts_with = "web")]
+    pub watch: Option<std::time::Duration>,
+
+    /// Table columns to show, in order (comma-separated, e.g. name,price,status)
+    /// [default: retailer,name,status,price,last,link]
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Vec<TableColumn>,
+
+    /// How links are shown in the table
+    #[arg(long, value_enum, default_value = "auto")]
+    pub links: LinkStyle,
+
+    /// Compact table: no outer borders or row lines, shortened links and no
+    /// Last Available column unless --columns asks for it
+    #[arg(long)]
+    pub compact: bool,
+
+    /// Table width in characters (default: the terminal's width; no limit when not a terminal)
+    #[arg(long, value_name = "COLS", value_parser = clap::value_parser!(u16).range(40..))]
+    pub width: Option<u16>,
+
+    /// Render each listing through this template file instead of --format,
+    /// e.g. "{{title}} - {{price}} @ {{link}}"
+    #[arg(long, value_name = "FILE", conflicts_with = "diff")]
+    pub template: Option<PathBuf>,
+
+    /// Exit with code 2 when any model fails to load, even if others had matches
+    #[arg(long)]
+    pub fail_on_partial: bool,
+
     /// Run as a web server instead of a one-off CLI command
     #[arg(short = 'w', long)]
     pub web: bool,
@@ -136,7 +381,205 @@ pub struct Args {
     #[arg(long, value_parser = parse_socket_addr, default_value = "127.0.0.1:8080")]
     pub listen: SocketAddr,
 
+    /// Parse a saved listings page instead of fetching from nowinstock (used for every requested model)
+    #[arg(long, value_name = "PATH", conflicts_with = "from_dir")]
+    pub from_file: Option<PathBuf>,
+
+    /// Parse saved listings pages from a directory, one per model (e.g. 5090.html or rtx5090.html)
+    #[arg(long, value_name = "DIR")]
+    pub from_dir: Option<PathBuf>,
+
+    /// User-Agent header sent with every request
+    #[arg(long, default_value = USER_AGENT)]
+    pub user_agent: String,
+
+    /// Per-request timeout in seconds
+    #[arg(long, value_name = "SECS", default_value_t = 15, value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout: u64,
+
+    /// Total attempts per page before giving up on transient failures (1 disables retries)
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub max_attempts: u32,
+
+    /// Initial retry delay in milliseconds, doubled after each failed attempt
+    #[arg(long, value_name = "MS", default_value_t = 500)]
+    pub retry_base_delay: u64,
+
+    /// Upper bound on a single retry delay in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 10_000)]
+    pub retry_max_delay: u64,
+
+    /// Ignore the server's Retry-After header and always use exponential backoff
+    #[arg(long)]
+    pub ignore_retry_after: bool,
+
+    /// Disable TLS certificate verification (not recommended)
+    #[arg(long, env = "GPU_PRICECHECK_INSECURE", value_parser = clap::builder::BoolishValueParser::new())]
+    pub insecure: bool,
+
+    /// PEM bundle of additional trusted CA certificates (e.g. for a corporate proxy)
+    #[arg(long, value_name = "PATH", env = "GPU_PRICECHECK_CA_CERT")]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Proxy URL for all requests (http://, https:// or socks5://). Repeat to rotate through a pool per request.
+    /// Without this, HTTPS_PROXY/HTTP_PROXY/ALL_PROXY and NO_PROXY are respected.
+    #[arg(long, value_name = "URL")]
+    pub proxy: Vec<String>,
+
+    /// Save every fetched page (body plus URL, status, headers and timestamp) to this directory
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["from_file", "from_dir", "replay"])]
+    pub record: Option<PathBuf>,
+
+    /// Serve pages previously saved with --record instead of fetching, keyed by URL
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["from_file", "from_dir"])]
+    pub replay: Option<PathBuf>,
+
+    /// How long the web server reuses a fetched page before fetching it again, in seconds, for both page
+    /// views and background refreshes (0 disables caching)
+    #[arg(long, value_name = "SECS", default_value_t = 60)]
+    pub cache_ttl: u64,
+
+    /// How often the web server refreshes each model in the background, in seconds (0 fetches on page view instead)
+    #[arg(long, value_name = "SECS", default_value_t = 300)]
+    pub refresh_interval: u64,
+
+    /// Per-model background refresh interval overriding --refresh-interval (e.g. 5090=60; repeatable)
+    #[arg(long, value_name = "MODEL=SECS", value_parser = parse_model_interval)]
+    pub model_interval: Vec<(GpuModel, u64)>,
+
+    /// History database (SQLite) that every CLI run and web server refresh records its listings to,
+    /// and that `history`, the history pages, alerts and the digest read
+    /// (default: $XDG_DATA_HOME/gpu_pricecheck/history.db)
+    #[arg(long, value_name = "PATH", global = true, env = "GPU_PRICECHECK_DB")]
+    pub db: Option<PathBuf>,
+
+    /// Don't record this run's listings (or this server's refreshes) in the history database
+    #[arg(long)]
+    pub no_history: bool,
+
+    /// Alert rules file (TOML, YAML or JSON) evaluated against every fresh scrape (CLI and web server)
+    #[arg(long, value_name = "PATH", env = "GPU_PRICECHECK_ALERTS")]
+    pub alerts: Option<PathBuf>,
+
+    /// POST a JSON payload for each fired alert to this URL (repeatable)
+    #[arg(long, value_name = "URL", global = true)]
+    pub webhook: Vec<String>,
+
+    /// Extra header sent with webhook requests, e.g. "Authorization: Bearer abc" (repeatable)
+    #[arg(long, value_name = "NAME: VALUE", value_parser = parse_header, global = true)]
+    pub webhook_header: Vec<(String, String)>,
+
+    /// Sign webhook bodies with HMAC-SHA256 using this secret (X-Gpu-Pricecheck-Signature header)
+    #[arg(long, value_name = "SECRET", env = "GPU_PRICECHECK_WEBHOOK_SECRET", hide_env_values = true, global = true)]
+    pub webhook_secret: Option<String>,
+
+    /// Post each fired alert to this Discord webhook as an embed (repeatable)
+    #[arg(long, value_name = "URL", global = true)]
+    pub discord_webhook: Vec<String>,
+
+    /// Post each fired alert to this Slack incoming webhook (repeatable)
+    #[arg(long, value_name = "URL", global = true)]
+    pub slack_webhook: Vec<String>,
+
+    /// Publish each fired alert to this ntfy topic URL, e.g. https://ntfy.sh/my-topic (repeatable)
+    #[arg(long, value_name = "TOPIC_URL", global = true)]
+    pub ntfy: Vec<String>,
+
+    /// Access token for protected ntfy topics
+    #[arg(long, value_name = "TOKEN", env = "GPU_PRICECHECK_NTFY_TOKEN", hide_env_values = true, global = true)]
+    pub ntfy_token: Option<String>,
+
+    /// SMTP server used for alert and digest emails
+    #[arg(long, value_name = "HOST", env = "GPU_PRICECHECK_SMTP_HOST", global = true)]
+    pub smtp_host: Option<String>,
+
+    /// SMTP port (default: 587 for starttls, 465 for tls, 25 for none)
+    #[arg(long, value_name = "PORT", env = "GPU_PRICECHECK_SMTP_PORT", global = true)]
+    pub smtp_port: Option<u16>,
+
+    /// How the SMTP connection is secured
+    #[arg(long, value_enum, default_value = "starttls", global = true)]
+    pub smtp_security: SmtpSecurity,
+
+    /// SMTP user name
+    #[arg(long, value_name = "USER", env = "GPU_PRICECHECK_SMTP_USERNAME", global = true)]
+    pub smtp_username: Option<String>,
+
+    /// SMTP password; prefer the environment variable over the flag
+    #[arg(long, value_name = "PASSWORD", env = "GPU_PRICECHECK_SMTP_PASSWORD", hide_env_values = true, global = true)]
+    pub smtp_password: Option<String>,
+
+    /// Sender address, e.g. "GPU Alerts <alerts@example.com>" (default: the SMTP user name)
+    #[arg(long, value_name = "ADDRESS", env = "GPU_PRICECHECK_EMAIL_FROM", global = true)]
+    pub email_from: Option<String>,
+
+    /// Email fired alerts (and digests) to this address (repeatable)
+    #[arg(long, value_name = "ADDRESS", global = true)]
+    pub email_to: Vec<String>,
+
+    /// Also email a daily digest at this local time (HH:MM) while watching or serving the web UI
+    #[arg(long, value_name = "HH:MM", value_parser = parse_time_of_day, global = true)]
+    pub email_digest_at: Option<chrono::NaiveTime>,
+
+    /// Only send digests by email, not an email per fired alert
+    #[arg(long, global = true)]
+    pub no_email_alerts: bool,
+
     /// Enable verbose logging output (default is minimal logging)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub verbose: bool,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_since_accepts_each_unit() {
+        assert_eq!(parse_since("30m"), Ok(chrono::Duration::minutes(30)));
+        assert_eq!(parse_since("12h"), Ok(chrono::Duration::hours(12)));
+        assert_eq!(parse_since("7d"), Ok(chrono::Duration::days(7)));
+        assert_eq!(parse_since("7"), Ok(chrono::Duration::days(7)));
+        assert_eq!(parse_since("2w"), Ok(chrono::Duration::weeks(2)));
+    }
+
+    #[test]
+    fn parse_since_rejects_out_of_range_windows() {
+        for window in ["99999999999999d", "99999999999d", "9999999999999999w", "99999999999999999999m"] {
+            assert!(parse_since(window).is_err(), "{} should be rejected", window);
+        }
+        assert!(parse_since("99999999999999d").unwrap_err().contains("out of range"));
+    }
+
+    #[test]
+    fn parse_interval_converts_to_seconds() {
+        assert_eq!(parse_interval("45"), Ok(std::time::Duration::from_secs(45)));
+        assert_eq!(parse_interval("30s"), Ok(std::time::Duration::from_secs(30)));
+        assert_eq!(parse_interval("5m"), Ok(std::time::Duration::from_secs(300)));
+        assert_eq!(parse_interval("1h"), Ok(std::time::Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn parse_interval_rejects_zero_and_overflow() {
+        for interval in ["0", "0s", "0m", "0h"] {
+            assert!(parse_interval(interval).unwrap_err().contains("greater than zero"), "{}", interval);
+        }
+        for interval in ["99999999999999999h", "999999999999999999m"] {
+            assert!(parse_interval(interval).unwrap_err().contains("out of range"), "{}", interval);
+        }
+    }
+
+    #[test]
+    fn timeout_must_be_at_least_one_second() {
+        use clap::Parser;
+        assert!(Args::try_parse_from(["gpu_pricecheck", "--timeout", "0"]).is_err());
+        assert_eq!(Args::try_parse_from(["gpu_pricecheck", "--timeout", "1"]).unwrap().timeout, 1);
+    }
+
+    #[test]
+    fn parse_since_rejects_bad_input() {
+        assert!(parse_since("bogus").is_err());
+        assert!(parse_since("5y").is_err());
+        assert!(parse_since("").is_err());
+    }
+}
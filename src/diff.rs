@@ -0,0 +1,261 @@
+use crate::cli::GpuModel;
+use crate::scraper::{GpuListing, StockStatus};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    /// Not in the previous scrape.
+    Added,
+    /// Status and/or price moved.
+    Changed,
+    /// In the previous scrape but gone from the page.
+    Removed,
+}
+
+impl DiffKind {
+    /// Marker shown in the change column, e.g. "+ New".
+    pub fn label(&self) -> &'static str {
+        match self {
+            DiffKind::Added => "+ New",
+            DiffKind::Changed => "~ Changed",
+            DiffKind::Removed => "- Removed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Transition<T> {
+    pub from: T,
+    pub to: T,
+}
+
+/// One difference between the previous scrape and the current listings.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListingChange {
+    pub model: GpuModel,
+    pub kind: DiffKind,
+    /// The current listing, or the last seen one for removed listings.
+    pub listing: GpuListing,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_change: Option<Transition<StockStatus>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_change: Option<Transition<Option<f64>>>,
+}
+
+impl ListingChange {
+    /// True when the price went down (only meaningful for price moves).
+    pub fn price_dropped(&self) -> bool {
+        matches!(&self.price_change, Some(Transition { from: Some(from), to: Some(to) }) if to < from)
+    }
+
+    /// The status, or the transition (e.g. "Out of Stock → In Stock").
+    pub fn status_text(&self) -> String {
+        match &self.status_change {
+            Some(t) => format!("{} → {}", t.from, t.to),
+            None => self.listing.status.to_string(),
+        }
+    }
+
+    /// The price, or the move with an arrow (e.g. "$2099.99 → $1999.99 ▼").
+    pub fn price_text(&self) -> String {
+        let price = |p: Option<f64>| p.map_or("-".to_string(), |p| format!("${:.2}", p));
+        match &self.price_change {
+            Some(t) => {
+                let arrow = if self.price_dropped() { "▼" } else { "▲" };
+                format!("{} → {} {}", price(t.from), price(t.to), arrow)
+            }
+            None => self.listing.price.clone(),
+        }
+    }
+}
+
+fn status_or_price_moved(before: &GpuListing, after: &GpuListing) -> bool {
+    before.status != after.status || price_moved(before.price_numeric, after.price_numeric)
+}
+
+/// Identities of the listings in `current` that are new or whose status or
+/// price moved since `previous`.
+pub fn changed_identities<'a>(previous: &[GpuListing], current: &'a [GpuListing]) -> HashSet<&'a str> {
+    // Like `diff_listings`, the first listing with an identity is the one compared.
+    let mut previous_by_id: HashMap<&str, &GpuListing> = HashMap::new();
+    for listing in previous {
+        previous_by_id.entry(listing.identity()).or_insert(listing);
+    }
+    current
+        .iter()
+        .filter(|l| previous_by_id.get(l.identity()).is_none_or(|before| status_or_price_moved(before, l)))
+        .map(GpuListing::identity)
+        .collect()
+}
+
+fn price_moved(from: Option<f64>, to: Option<f64>) -> bool {
+    match (from, to) {
+        (Some(from), Some(to)) => (from - to).abs() >= 0.005,
+        (from, to) => from.is_some() != to.is_some(),
+    }
+}
+
+/// Compares two sets of listings by identity. Added and changed listings come
+/// in `current` order, followed by removed ones in `previous` order.
+pub fn diff_listings(model: GpuModel, previous: &[GpuListing], current: &[GpuListing]) -> Vec<ListingChange> {
+    let mut previous_by_id: HashMap<&str, &GpuListing> = HashMap::new();
+    for listing in previous {
+        previous_by_id.entry(listing.identity()).or_insert(listing);
+    }
+    let mut seen = HashSet::new();
+    let mut changes = Vec::new();
+    for listing in current {
+        if !seen.insert(listing.identity()) {
+            continue;
+        }
+        let Some(before) = previous_by_id.get(listing.identity()) else {
+            changes.push(ListingChange {
+                model,
+                kind: DiffKind::Added,
+                listing: listing.clone(),
+                status_change: None,
+                price_change: None,
+            });
+            continue;
+        };
+        let status_change = (before.status != listing.status).then(|| Transition {
+            from: before.status.clone(),
+            to: listing.status.clone(),
+        });
+        let price_change = price_moved(before.price_numeric, listing.price_numeric).then_some(Transition {
+            from: before.price_numeric,
+            to: listing.price_numeric,
+        });
+        if status_change.is_some() || price_change.is_some() {
+            changes.push(ListingChange {
+                model,
+                kind: DiffKind::Changed,
+                listing: listing.clone(),
+                status_change,
+                price_change,
+            });
+        }
+    }
+    for listing in previous {
+        if seen.insert(listing.identity()) {
+            changes.push(ListingChange {
+                model,
+                kind: DiffKind::Removed,
+                listing: listing.clone(),
+                status_change: None,
+                price_change: None,
+            });
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(changes: &[ListingChange]) -> Vec<(DiffKind, &str)> {
+        changes.iter().map(|c| (c.kind, c.listing.link.trim_start_matches("https://example.com/"))).collect()
+    }
+
+    #[test]
+    fn reports_added_changed_and_removed_listings() {
+        let previous = [
+            GpuListing::for_test("same", StockStatus::InStock, Some(1999.0)),
+            GpuListing::for_test("restock", StockStatus::OutOfStock, Some(2099.0)),
+            GpuListing::for_test("gone", StockStatus::InStock, Some(2199.0)),
+        ];
+        let current = [
+            GpuListing::for_test("new", StockStatus::Preorder, Some(2299.0)),
+            GpuListing::for_test("restock", StockStatus::InStock, Some(1899.0)),
+            GpuListing::for_test("same", StockStatus::InStock, Some(1999.0)),
+        ];
+        let changes = diff_listings(GpuModel::Rtx5090, &previous, &current);
+        assert_eq!(
+            summary(&changes),
+            vec![(DiffKind::Added, "new"), (DiffKind::Changed, "restock"), (DiffKind::Removed, "gone")]
+        );
+
+        let added = &changes[0];
+        assert!(added.status_change.is_none() && added.price_change.is_none());
+        let restock = &changes[1];
+        assert_eq!(restock.status_text(), "Out of Stock → In Stock");
+        assert_eq!(restock.price_text(), "$2099.00 → $1899.00 ▼");
+        assert!(restock.price_dropped());
+        assert_eq!(changes[2].listing.status, StockStatus::InStock);
+
+        assert_eq!(
+            changed_identities(&previous, &current),
+            HashSet::from(["https://example.com/new", "https://example.com/restock"])
+        );
+    }
+
+    #[test]
+    fn status_only_and_price_only_changes() {
+        let previous = [
+            GpuListing::for_test("a", StockStatus::InStock, Some(1999.0)),
+            GpuListing::for_test("b", StockStatus::InStock, None),
+        ];
+        let current = [
+            GpuListing::for_test("a", StockStatus::OutOfStock, Some(1999.0)),
+            GpuListing::for_test("b", StockStatus::InStock, Some(2099.0)),
+        ];
+        let changes = diff_listings(GpuModel::Rtx5090, &previous, &current);
+        assert_eq!(summary(&changes), vec![(DiffKind::Changed, "a"), (DiffKind::Changed, "b")]);
+        assert!(changes[0].price_change.is_none());
+        assert_eq!(changes[0].price_text(), "$1999.00");
+        assert!(changes[1].status_change.is_none());
+        assert_eq!(changes[1].price_text(), "- → $2099.00 ▲");
+        assert!(!changes[1].price_dropped());
+    }
+
+    #[test]
+    fn price_moves_under_half_a_cent_are_ignored() {
+        assert!(!price_moved(Some(1999.99), Some(1999.994)));
+        assert!(price_moved(Some(1999.99), Some(1999.98)));
+        assert!(!price_moved(None, None));
+        assert!(price_moved(Some(1999.99), None));
+
+        let previous = [GpuListing::for_test("a", StockStatus::InStock, Some(1999.99))];
+        let current = [GpuListing::for_test("a", StockStatus::InStock, Some(1999.991))];
+        assert!(diff_listings(GpuModel::Rtx5090, &previous, &current).is_empty());
+        assert!(changed_identities(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn duplicate_identities_are_compared_once() {
+        // The first occurrence on either side is the one compared.
+        let previous = [
+            GpuListing::for_test("a", StockStatus::OutOfStock, Some(1999.0)),
+            GpuListing::for_test("a", StockStatus::InStock, Some(1999.0)),
+            GpuListing::for_test("gone", StockStatus::InStock, Some(2199.0)),
+            GpuListing::for_test("gone", StockStatus::InStock, Some(2199.0)),
+        ];
+        let current = [
+            GpuListing::for_test("a", StockStatus::InStock, Some(1999.0)),
+            GpuListing::for_test("a", StockStatus::InStock, Some(1899.0)),
+            GpuListing::for_test("new", StockStatus::InStock, Some(2299.0)),
+            GpuListing::for_test("new", StockStatus::InStock, Some(2299.0)),
+        ];
+        let changes = diff_listings(GpuModel::Rtx5090, &previous, &current);
+        assert_eq!(
+            summary(&changes),
+            vec![(DiffKind::Changed, "a"), (DiffKind::Added, "new"), (DiffKind::Removed, "gone")]
+        );
+        assert!(changes[0].price_change.is_none());
+        assert_eq!(changes[0].status_text(), "Out of Stock → In Stock");
+        assert_eq!(
+            changed_identities(&previous, &current),
+            HashSet::from(["https://example.com/a", "https://example.com/new"])
+        );
+    }
+
+    #[test]
+    fn unchanged_listings_produce_no_changes() {
+        let listings = [GpuListing::for_test("a", StockStatus::InStock, Some(1999.0))];
+        assert!(diff_listings(GpuModel::Rtx5090, &listings, &listings).is_empty());
+        assert!(diff_listings(GpuModel::Rtx5090, &[], &[]).is_empty());
+    }
+}
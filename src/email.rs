@@ -0,0 +1,487 @@
+use crate::alerts::{self, AlertEvent};
+use crate::cli::{Args, GpuModel, SmtpSecurity};
+use crate::fetcher::{FetchConfig, RetryPolicy};
+use crate::history::{self, ChangeKind};
+use crate::scraper::GpuListing;
+use crate::storage::Storage;
+use anyhow::{anyhow, Context, Result};
+use askama::Template;
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Utc};
+use clap::ValueEnum;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Price moves smaller than this (in percent, net over the digest window)
+/// are left out of the digest.
+const NOTABLE_CHANGE_PERCENT: f64 = 2.0;
+/// At most this many price changes are listed in a digest.
+const MAX_DIGEST_CHANGES: usize = 20;
+
+/// Sends alert and digest emails over SMTP. Cloning is cheap and clones
+/// share the connection pool.
+#[derive(Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Arc<Vec<Mailbox>>,
+    retry: RetryPolicy,
+    quiet: bool,
+}
+
+impl Mailer {
+    /// Builds the mailer from the `--smtp-*`/`--email-*` options, or returns
+    /// `None` when email isn't configured.
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let host = match (&args.smtp_host, args.email_to.is_empty()) {
+            (None, true) => return Ok(None),
+            (Some(host), false) => host,
+            (Some(_), true) => anyhow::bail!("--smtp-host is set but no --email-to recipients are"),
+            (None, false) => anyhow::bail!("--email-to needs an SMTP server (use --smtp-host)"),
+        };
+        let parse_mailbox = |address: &str| -> Result<Mailbox> {
+            address.parse().with_context(|| format!("Invalid email address: {}", address))
+        };
+        let from = args
+            .email_from
+            .as_deref()
+            .or(args.smtp_username.as_deref().filter(|user| user.contains('@')))
+            .ok_or_else(|| anyhow!("--email-from is required unless the SMTP user name is an email address"))?;
+        let to = args.email_to.iter().map(|address| parse_mailbox(address)).collect::<Result<Vec<_>>>()?;
+
+        let tls = || -> Result<TlsParameters> {
+            TlsParameters::builder(host.clone())
+                .dangerous_accept_invalid_certs(args.insecure)
+                .build()
+                .context("Failed to set up SMTP TLS")
+        };
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host.as_str())
+            .port(args.smtp_port.unwrap_or_else(|| args.smtp_security.default_port()))
+            .timeout(Some(Duration::from_secs(args.timeout)))
+            .tls(match args.smtp_security {
+                SmtpSecurity::Starttls => Tls::Required(tls()?),
+                SmtpSecurity::Tls => Tls::Wrapper(tls()?),
+                SmtpSecurity::None => Tls::None,
+            });
+        match (&args.smtp_username, &args.smtp_password) {
+            (Some(user), Some(password)) => {
+                builder = builder.credentials(Credentials::new(user.clone(), password.clone()));
+            }
+            (Some(_), None) => anyhow::bail!("--smtp-username is set but no password (set GPU_PRICECHECK_SMTP_PASSWORD)"),
+            (None, Some(_)) => anyhow::bail!("An SMTP password is set but no --smtp-username"),
+            (None, None) => {}
+        }
+        Ok(Some(Mailer {
+            transport: builder.build(),
+            from: parse_mailbox(from)?,
+            to: Arc::new(to),
+            retry: FetchConfig::from_args(args).retry,
+            quiet: !args.verbose,
+        }))
+    }
+
+    /// Emails one fired alert to every recipient.
+    pub async fn send_alert(&self, event: &AlertEvent) -> Result<()> {
+        let subject = format!("[gpu_pricecheck] {}: {}", event.rule, event.model.cli_name());
+        self.send(&subject, alert_text(event), None).await
+    }
+
+    pub async fn send_digest(&self, digest: &Digest) -> Result<()> {
+        let subject = format!("[gpu_pricecheck] Daily digest for {}", digest.generated_at.format("%Y-%m-%d"));
+        let (text, html) = digest.render()?;
+        self.send(&subject, text, Some(html)).await
+    }
+
+    /// Sends a message, retrying transient failures (timeouts, connection
+    /// errors, 4xx replies) with the same backoff as page fetches.
+    async fn send(&self, subject: &str, text: String, html: Option<String>) -> Result<()> {
+        let mut builder = Message::builder().from(self.from.clone()).subject(subject);
+        for to in self.to.iter() {
+            builder = builder.to(to.clone());
+        }
+        let message = match html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(text, html)),
+            None => builder.body(text),
+        }
+        .context("Failed to build email")?;
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let error = match self.transport.send(message.clone()).await {
+                Ok(_) => return Ok(()),
+                Err(e) => e,
+            };
+            if error.is_permanent() || error.is_client() || attempt >= self.retry.max_attempts {
+                return Err(anyhow::Error::new(error).context(format!("Failed to send email \"{}\"", subject)));
+            }
+            let delay = self.retry.backoff(attempt);
+            if !self.quiet {
+                println!(
+                    "Email attempt {}/{} failed ({}); retrying in {:.1}s",
+                    attempt,
+                    self.retry.max_attempts,
+                    error,
+                    delay.as_secs_f64()
+                );
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn alert_text(event: &AlertEvent) -> String {
+    let mut text = format!("{}\n\nRule: {}\nModel: {}\n", event.message, event.rule, event.model.cli_name());
+    for listing in &event.listings {
+        text.push_str(&format!(
+            "\n{}\n  {} at {} ({})\n  {}\n",
+            listing.title,
+            listing.price,
+            listing.retailer_display(),
+            listing.status,
+            listing.link
+        ));
+    }
+    text.push_str(&format!("\nFired at {}\n", event.fired_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")));
+    text
+}
+
+/// One model's row in the digest.
+pub struct DigestModel {
+    pub model: GpuModel,
+    /// The cheapest available listing in the most recent scrape of the window.
+    pub cheapest: Option<GpuListing>,
+    /// When that scrape happened; `None` if the model wasn't checked in the window.
+    pub checked_at: Option<DateTime<Local>>,
+}
+
+/// A listing whose price moved notably over the digest window.
+pub struct DigestPriceChange {
+    pub model: GpuModel,
+    pub title: String,
+    pub retailer: String,
+    pub link: String,
+    pub from: f64,
+    pub to: f64,
+}
+
+impl DigestPriceChange {
+    pub fn percent(&self) -> f64 {
+        (self.to - self.from) / self.from * 100.0
+    }
+
+    pub fn dropped(&self) -> bool {
+        self.to < self.from
+    }
+}
+
+/// Summary of the history database over a window (usually the last 24 hours).
+pub struct Digest {
+    pub generated_at: DateTime<Local>,
+    pub since: DateTime<Local>,
+    pub models: Vec<DigestModel>,
+    /// Biggest drops first, then rises.
+    pub price_changes: Vec<DigestPriceChange>,
+}
+
+#[derive(Template)]
+#[template(path = "digest.html")]
+struct DigestHtml<'a> {
+    digest: &'a Digest,
+}
+
+#[derive(Template)]
+#[template(path = "digest.txt")]
+struct DigestText<'a> {
+    digest: &'a Digest,
+}
+
+impl Digest {
+    /// Builds a digest from everything the CLI and web server logged since `now - window`.
+    pub fn build(storage: &Storage, window: chrono::Duration) -> Result<Self> {
+        let generated_at = Local::now();
+        let since: DateTime<Utc> = (generated_at - window).into();
+        let mut by_model: HashMap<GpuModel, Vec<history::Observation>> = HashMap::new();
+        for observation in storage.observations_with_baseline(None, since)? {
+            by_model.entry(observation.model).or_default().push(observation);
+        }
+        let mut models = Vec::new();
+        let mut price_changes = Vec::new();
+        for &model in GpuModel::value_variants() {
+            let observations = by_model.remove(&model).unwrap_or_default();
+            let checked_at = observations.iter().map(|o| o.scraped_at).filter(|&t| t >= since).max();
+            let latest: Vec<GpuListing> = observations
+                .iter()
+                .filter(|o| Some(o.scraped_at) == checked_at)
+                .map(|o| o.listing.clone())
+                .collect();
+            models.push(DigestModel {
+                model,
+                cheapest: alerts::cheapest_available(&latest).cloned(),
+                checked_at: checked_at.map(|t| t.with_timezone(&Local)),
+            });
+            price_changes.extend(net_price_changes(model, &observations, since));
+        }
+        price_changes.sort_by(|a, b| a.percent().total_cmp(&b.percent()));
+        price_changes.truncate(MAX_DIGEST_CHANGES);
+        Ok(Digest { generated_at, since: since.with_timezone(&Local), models, price_changes })
+    }
+
+    /// Renders the plain-text and HTML bodies.
+    pub fn render(&self) -> Result<(String, String)> {
+        let text = DigestText { digest: self }.render().context("Failed to render text digest")?;
+        let html = DigestHtml { digest: self }.render().context("Failed to render HTML digest")?;
+        Ok((text, html))
+    }
+}
+
+/// Each listing's net price move over the window (first price before the
+/// first change to the last price), keeping only notable ones.
+fn net_price_changes(
+    model: GpuModel,
+    observations: &[history::Observation],
+    since: DateTime<Utc>,
+) -> Vec<DigestPriceChange> {
+    let mut changes: Vec<DigestPriceChange> = Vec::new();
+    let mut by_link: HashMap<String, usize> = HashMap::new();
+    for event in history::build_timeline(observations, Some(since)) {
+        let (ChangeKind::PriceChange, Some(from), Some(to)) = (event.kind, event.from_price, event.to_price) else {
+            continue;
+        };
+        match by_link.get(&event.link) {
+            Some(&index) => changes[index].to = to,
+            None => {
+                by_link.insert(event.link.clone(), changes.len());
+                changes.push(DigestPriceChange {
+                    model,
+                    title: event.title,
+                    retailer: event.retailer.unwrap_or_else(|| "-".to_string()),
+                    link: event.link,
+                    from,
+                    to,
+                });
+            }
+        }
+    }
+    changes.retain(|change| change.from > 0.0 && change.percent().abs() >= NOTABLE_CHANGE_PERCENT);
+    changes
+}
+
+/// The next time the clock (in `now`'s time zone) reads `at`, strictly after `now`.
+fn next_occurrence<Tz: TimeZone>(at: NaiveTime, now: DateTime<Tz>) -> DateTime<Tz> {
+    let mut date = now.date_naive();
+    loop {
+        // Skips times that don't exist on a day (DST gaps).
+        if let Some(next) =
+            now.timezone().from_local_datetime(&date.and_time(at)).earliest().filter(|next| *next > now)
+        {
+            return next;
+        }
+        date = date.succ_opt().expect("date in range");
+    }
+}
+
+/// The running daily digest task. Dropping it stops the task, so it doesn't
+/// outlive the server or `--watch` loop that started it.
+pub struct DigestTask(tokio::task::JoinHandle<()>);
+
+impl Drop for DigestTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Starts the daily digest task when `--email-digest-at` and email are configured.
+pub fn spawn_daily_digest(args: &Args, storage: &Arc<Storage>) -> Result<Option<DigestTask>> {
+    let Some(at) = args.email_digest_at else {
+        return Ok(None);
+    };
+    let Some(mailer) = Mailer::from_args(args)? else {
+        anyhow::bail!("--email-digest-at needs email to be configured (use --smtp-host and --email-to)");
+    };
+    Ok(Some(DigestTask(tokio::spawn(run_daily_digest(mailer, storage.clone(), at)))))
+}
+
+async fn run_daily_digest(mailer: Mailer, storage: Arc<Storage>, at: NaiveTime) {
+    loop {
+        let now = Local::now();
+        let next = next_occurrence(at, now);
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+        let storage = storage.clone();
+        let digest = tokio::task::spawn_blocking(move || Digest::build(&storage, chrono::Duration::days(1)))
+            .await
+            .map_err(anyhow::Error::new)
+            .and_then(|digest| digest);
+        let sent = match digest {
+            Ok(digest) => mailer.send_digest(&digest).await,
+            Err(e) => Err(e.context("Failed to build digest")),
+        };
+        match sent {
+            Ok(()) if !mailer.quiet => println!("Sent daily digest email."),
+            Ok(()) => {}
+            Err(e) => eprintln!("Daily digest failed: {:#}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraper::StockStatus;
+    use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime};
+    use std::path::PathBuf;
+
+    /// US Eastern time in 2026: clocks spring forward from 02:00 to 03:00 on
+    /// March 8 and fall back from 02:00 to 01:00 on November 1.
+    #[derive(Debug, Clone, Copy)]
+    struct Eastern;
+
+    impl Eastern {
+        fn offset_at(utc: &NaiveDateTime) -> FixedOffset {
+            let summer_start = NaiveDate::from_ymd_opt(2026, 3, 8).unwrap().and_hms_opt(7, 0, 0).unwrap();
+            let summer_end = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap().and_hms_opt(6, 0, 0).unwrap();
+            let hours = if (summer_start..summer_end).contains(utc) { -4 } else { -5 };
+            FixedOffset::east_opt(hours * 3600).unwrap()
+        }
+    }
+
+    impl TimeZone for Eastern {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Eastern
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let candidates: Vec<FixedOffset> = [-4, -5]
+                .into_iter()
+                .map(|hours| FixedOffset::east_opt(hours * 3600).unwrap())
+                .filter(|offset| Eastern::offset_at(&(*local - *offset)) == *offset)
+                .collect();
+            match candidates[..] {
+                [] => LocalResult::None,
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest, ..] => LocalResult::Ambiguous(earliest, latest),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            Eastern::offset_at(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            Eastern::offset_at(utc)
+        }
+    }
+
+    fn eastern(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Eastern> {
+        Eastern.with_ymd_and_hms(2026, month, day, hour, minute, 0).earliest().unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn next_occurrence_is_later_today_or_tomorrow() {
+        assert_eq!(next_occurrence(time(8, 0), eastern(6, 15, 7, 0)), eastern(6, 15, 8, 0));
+        assert_eq!(next_occurrence(time(8, 0), eastern(6, 15, 9, 0)), eastern(6, 16, 8, 0));
+        // Strictly after now, so a digest that just went out isn't sent again.
+        assert_eq!(next_occurrence(time(8, 0), eastern(6, 15, 8, 0)), eastern(6, 16, 8, 0));
+        assert_eq!(
+            next_occurrence(time(0, 0), eastern(12, 31, 23, 59)).date_naive(),
+            NaiveDate::from_ymd_opt(2027, 1, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn next_occurrence_skips_dst_gaps_and_takes_the_first_repeat() {
+        // 02:30 doesn't exist on March 8, so the next one is on March 9.
+        assert_eq!(next_occurrence(time(2, 30), eastern(3, 8, 0, 0)), eastern(3, 9, 2, 30));
+        assert_eq!(next_occurrence(time(3, 0), eastern(3, 8, 0, 0)).naive_utc().to_string(), "2026-03-08 07:00:00");
+        // 01:30 happens twice on November 1; the digest goes out at the first.
+        let repeated = next_occurrence(time(1, 30), eastern(11, 1, 0, 0));
+        assert_eq!(repeated.naive_utc().to_string(), "2026-11-01 05:30:00");
+        assert_eq!(next_occurrence(time(1, 30), repeated).date_naive(), NaiveDate::from_ymd_opt(2026, 11, 2).unwrap());
+    }
+
+    fn observation(scraped_at: DateTime<Utc>, listing: GpuListing) -> history::Observation {
+        history::Observation { scraped_at, model: GpuModel::Rtx5090, listing }
+    }
+
+    #[test]
+    fn net_price_changes_keep_notable_net_moves() {
+        let since = Utc::now() - chrono::Duration::days(1);
+        let hours = |h| since + chrono::Duration::hours(h);
+        let observations = [
+            // Baseline before the window, then two drops: net 1000 → 900.
+            observation(hours(-2), GpuListing::for_test("a", StockStatus::InStock, Some(1000.0))),
+            observation(hours(1), GpuListing::for_test("a", StockStatus::InStock, Some(980.0))),
+            observation(hours(2), GpuListing::for_test("a", StockStatus::OutOfStock, Some(900.0))),
+            // Up 5% and back down: no net move.
+            observation(hours(1), GpuListing::for_test("b", StockStatus::InStock, Some(2000.0))),
+            observation(hours(2), GpuListing::for_test("b", StockStatus::InStock, Some(2100.0))),
+            observation(hours(3), GpuListing::for_test("b", StockStatus::InStock, Some(2000.0))),
+            // 1% isn't notable.
+            observation(hours(1), GpuListing::for_test("c", StockStatus::InStock, Some(2000.0))),
+            observation(hours(2), GpuListing::for_test("c", StockStatus::InStock, Some(2020.0))),
+            // Moves before the window don't count.
+            observation(hours(-3), GpuListing::for_test("d", StockStatus::InStock, Some(3000.0))),
+            observation(hours(-2), GpuListing::for_test("d", StockStatus::InStock, Some(2500.0))),
+            observation(hours(2), GpuListing::for_test("d", StockStatus::InStock, Some(2500.0))),
+        ];
+        let changes = net_price_changes(GpuModel::Rtx5090, &observations, since);
+        assert_eq!(changes.len(), 1);
+        let change = &changes[0];
+        assert_eq!((change.link.as_str(), change.from, change.to), ("https://example.com/a", 1000.0, 900.0));
+        assert!((change.percent() + 10.0).abs() < 1e-9);
+        assert!(change.dropped());
+        assert_eq!(change.retailer, "Best Buy");
+    }
+
+    #[test]
+    fn digest_summarizes_the_window() {
+        let storage = Storage::new(PathBuf::from(":memory:"));
+        let ago = |hours| Utc::now() - chrono::Duration::hours(hours);
+        let cheap = GpuListing::for_test("cheap", StockStatus::InStock, Some(1900.0));
+        storage.record(GpuModel::Rtx5090, &[GpuListing::for_test("cheap", StockStatus::InStock, Some(2100.0))], ago(30)).unwrap();
+        storage
+            .record(GpuModel::Rtx5090, &[cheap.clone(), GpuListing::for_test("sold-out", StockStatus::OutOfStock, Some(1500.0))], ago(2))
+            .unwrap();
+        storage.record(GpuModel::Rtx5080, &[GpuListing::for_test("old", StockStatus::InStock, Some(999.0))], ago(30)).unwrap();
+        storage.record(GpuModel::Rtx5070, &[GpuListing::for_test("none", StockStatus::OutOfStock, Some(549.0))], ago(1)).unwrap();
+
+        let digest = Digest::build(&storage, chrono::Duration::days(1)).unwrap();
+        let row = |model| digest.models.iter().find(|row| row.model == model).unwrap();
+        assert_eq!(digest.models.len(), GpuModel::value_variants().len());
+        assert_eq!(row(GpuModel::Rtx5090).cheapest.as_ref().map(|l| l.link.as_str()), Some(cheap.link.as_str()));
+        assert!(row(GpuModel::Rtx5080).checked_at.is_none());
+        assert!(row(GpuModel::Rtx5070).checked_at.is_some() && row(GpuModel::Rtx5070).cheapest.is_none());
+        assert_eq!(digest.price_changes.len(), 1);
+        assert_eq!((digest.price_changes[0].from, digest.price_changes[0].to), (2100.0, 1900.0));
+
+        let (text, html) = digest.render().unwrap();
+        assert!(text.contains("5090: $1900.00 at Best Buy - RTX 5090 cheap"), "{}", text);
+        assert!(text.contains("5080: not checked in this period"), "{}", text);
+        assert!(text.contains("5070: nothing available"), "{}", text);
+        assert!(text.contains("5090: $2100.00 -> $1900.00 (-9.5%) at Best Buy - RTX 5090 cheap"), "{}", text);
+        assert!(html.contains(r#"<a href="https://example.com/cheap""#), "{}", html);
+        assert!(html.contains("-9.5%"), "{}", html);
+    }
+
+    #[test]
+    fn empty_digest_says_so() {
+        let storage = Storage::new(PathBuf::from(":memory:"));
+        let digest = Digest::build(&storage, chrono::Duration::days(1)).unwrap();
+        assert!(digest.models.iter().all(|row| row.checked_at.is_none()));
+        assert!(digest.render().unwrap().0.contains("No notable price changes."));
+    }
+}
@@ -0,0 +1,519 @@
+use crate::cassette::Cassette;
+use crate::cli::{Args, GpuModel};
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Once};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Certificate verification settings.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// Skip certificate verification entirely. Off by default; only for
+    /// deliberately opting out (e.g. debugging against a broken proxy).
+    pub accept_invalid_certs: bool,
+    /// Extra PEM bundle of trusted root certificates, e.g. a corporate
+    /// proxy's CA. Added on top of the system roots.
+    pub ca_bundle: Option<PathBuf>,
+}
+
+/// How failed requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts per URL, including the first one. 1 disables retries.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Wait as long as the server's `Retry-After` header asks (capped at `max_delay`).
+    pub honor_retry_after: bool,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with jitter: a random delay between half and all
+    /// of `base_delay * 2^(attempt - 1)`, capped at `max_delay`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let capped = exp.min(self.max_delay);
+        let half = capped / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    fn delay_for(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        match retry_after {
+            Some(wait) if self.honor_retry_after => wait.min(self.max_delay),
+            _ => self.backoff(attempt),
+        }
+    }
+}
+
+/// Settings used to build a `Fetcher`.
+#[derive(Debug, Clone)]
+pub struct FetchConfig {
+    pub user_agent: String,
+    pub timeout: Duration,
+    pub retry: RetryPolicy,
+    pub tls: TlsConfig,
+    /// Explicit proxy URLs (http, https or socks5), rotated per request.
+    /// When empty, the standard `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY`
+    /// environment variables are used.
+    pub proxies: Vec<String>,
+    /// When set, every response is saved to this cassette.
+    pub record: Option<Cassette>,
+}
+
+impl FetchConfig {
+    pub fn from_args(args: &Args) -> Self {
+        FetchConfig {
+            user_agent: args.user_agent.clone(),
+            timeout: Duration::from_secs(args.timeout),
+            retry: RetryPolicy {
+                max_attempts: args.max_attempts.max(1),
+                base_delay: Duration::from_millis(args.retry_base_delay),
+                max_delay: Duration::from_millis(args.retry_max_delay),
+                honor_retry_after: !args.ignore_retry_after,
+            },
+            tls: TlsConfig {
+                accept_invalid_certs: args.insecure,
+                ca_bundle: args.ca_cert.clone(),
+            },
+            proxies: args.proxy.clone(),
+            record: args.record.as_ref().map(Cassette::new),
+        }
+    }
+}
+
+/// A successfully loaded page.
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    pub body: String,
+    /// Number of attempts it took (1 when the first request succeeded).
+    pub attempts: u32,
+    /// When the page was actually retrieved (or recorded, for replays).
+    pub fetched_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Returned when a URL still fails after exhausting the retry policy.
+#[derive(Debug, Error)]
+#[error("{message} (gave up after {attempts} attempt(s))")]
+pub struct FetchError {
+    pub attempts: u32,
+    pub message: String,
+}
+
+/// Outcome of loading one model's listings, reported alongside the results
+/// so a failed or flaky fetch isn't mistaken for "no stock".
+#[derive(Debug, Clone)]
+pub struct ModelFetchReport {
+    pub model: GpuModel,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+impl ModelFetchReport {
+    pub fn success(model: GpuModel, attempts: u32) -> Self {
+        ModelFetchReport { model, attempts, error: None }
+    }
+
+    /// Builds a failure report, taking the attempt count from a `FetchError`
+    /// in the error chain (parse and file errors count as one attempt).
+    pub fn failure(model: GpuModel, error: &anyhow::Error) -> Self {
+        let attempts = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<FetchError>())
+            .map_or(1, |fe| fe.attempts);
+        ModelFetchReport { model, attempts, error: Some(format!("{:#}", error)) }
+    }
+
+    /// One-line human-readable summary, e.g. "5090: succeeded after 2 attempts".
+    pub fn summary(&self) -> String {
+        match &self.error {
+            // Fetch errors already mention the attempt count.
+            Some(error) => format!("{}: failed: {}", self.model.cli_name(), error),
+            None => format!("{}: succeeded after {} attempts", self.model.cli_name(), self.attempts),
+        }
+    }
+
+    /// Whether this model needs to be mentioned (it failed or needed retries).
+    pub fn is_notable(&self) -> bool {
+        self.error.is_some() || self.attempts > 1
+    }
+}
+
+/// Why a single attempt failed, and whether it's worth trying again.
+struct AttemptFailure {
+    error: anyhow::Error,
+    retryable: bool,
+    retry_after: Option<Duration>,
+}
+
+/// Timeouts, connection errors, 429 and 5xx are treated as transient.
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// GETs are safe to repeat, so a connection reset or closed while sending
+/// the request or reading the body is retried too.
+fn is_retryable_get_error(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect() || err.is_request() || err.is_body()
+}
+
+/// A POST whose connection dropped mid-request may already have been acted
+/// on, and retrying it could deliver it twice, so only timeouts and failures
+/// to connect are retried.
+fn is_retryable_post_error(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect()
+}
+
+/// Parses `Retry-After` as either delay-seconds or an HTTP date.
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().ok()
+}
+
+/// Hides any password in a proxy URL so it can be logged.
+fn redact_proxy(proxy: &str) -> String {
+    match reqwest::Url::parse(proxy) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("***"));
+            url.to_string()
+        }
+        _ => proxy.to_string(),
+    }
+}
+
+/// Describes the proxy reqwest will pick up from the environment, for logging.
+fn env_proxy_label() -> Option<String> {
+    ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+        .map(|proxy| format!("{} (from environment)", redact_proxy(&proxy)))
+}
+
+/// A pooled client bound to one proxy (or none).
+#[derive(Debug)]
+struct ProxiedClient {
+    client: reqwest::Client,
+    /// Proxy description for verbose logs.
+    label: Option<String>,
+}
+
+/// The single place outbound HTTP goes through.
+///
+/// Wraps one `reqwest::Client` per configured proxy (usually just one) so
+/// connections and TLS sessions are pooled across requests. Cloning is cheap
+/// and shares the same pools.
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    clients: Arc<Vec<ProxiedClient>>,
+    /// Round-robin cursor into `clients`.
+    next_client: Arc<AtomicUsize>,
+    retry: RetryPolicy,
+    record: Option<Cassette>,
+}
+
+impl Fetcher {
+    pub fn new(config: FetchConfig) -> Result<Self> {
+        let mut root_certs = Vec::new();
+        if let Some(path) = &config.tls.ca_bundle {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("Failed to read CA bundle {}", path.display()))?;
+            root_certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid PEM CA bundle {}", path.display()))?;
+            if root_certs.is_empty() {
+                anyhow::bail!("CA bundle {} contains no certificates", path.display());
+            }
+        }
+        if config.tls.accept_invalid_certs {
+            // Several fetchers may be built in one run; warn once.
+            static INSECURE_WARNING: Once = Once::new();
+            INSECURE_WARNING.call_once(|| {
+                eprintln!("Warning: TLS certificate verification is disabled (--insecure).");
+            });
+        }
+        let build_client = |proxy: Option<&str>| -> Result<reqwest::Client> {
+            let mut builder = reqwest::Client::builder()
+                .http1_only()
+                .user_agent(&config.user_agent)
+                .timeout(config.timeout);
+            for cert in &root_certs {
+                builder = builder.add_root_certificate(cert.clone());
+            }
+            if config.tls.accept_invalid_certs {
+                builder = builder.danger_accept_invalid_certs(true);
+            }
+            if let Some(proxy) = proxy {
+                let proxy = reqwest::Proxy::all(proxy)
+                    .with_context(|| format!("Invalid proxy URL {}", redact_proxy(proxy)))?
+                    .no_proxy(reqwest::NoProxy::from_env());
+                builder = builder.proxy(proxy);
+            }
+            builder.build().context("Failed to build HTTP client")
+        };
+        let clients = if config.proxies.is_empty() {
+            vec![ProxiedClient { client: build_client(None)?, label: env_proxy_label() }]
+        } else {
+            config
+                .proxies
+                .iter()
+                .map(|proxy| {
+                    Ok(ProxiedClient { client: build_client(Some(proxy))?, label: Some(redact_proxy(proxy)) })
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+        Ok(Fetcher {
+            clients: Arc::new(clients),
+            next_client: Arc::new(AtomicUsize::new(0)),
+            retry: config.retry,
+            record: config.record,
+        })
+    }
+
+    /// Picks the next client in rotation.
+    fn next_client(&self) -> &ProxiedClient {
+        let index = self.next_client.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        &self.clients[index]
+    }
+
+    /// Fetches `url`, retrying transient failures according to the retry policy.
+    pub async fn fetch_html(&self, url: &str, quiet: bool) -> Result<FetchedPage> {
+        let (body, attempts) = self.with_retries(url, quiet, || self.fetch_once(url, quiet)).await?;
+        Ok(FetchedPage { body, attempts, fetched_at: chrono::Local::now() })
+    }
+
+    /// POSTs `body` to `url` with `headers`, retrying transient failures like
+    /// page fetches. Returns the number of attempts made.
+    pub async fn post(&self, url: &str, headers: &HeaderMap, body: &str, quiet: bool) -> Result<u32> {
+        let (_, attempts) = self.with_retries(url, quiet, || self.post_once(url, headers, body)).await?;
+        Ok(attempts)
+    }
+
+    async fn with_retries<T, F, Fut>(&self, url: &str, quiet: bool, mut attempt_once: F) -> Result<(T, u32)>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, AttemptFailure>>,
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let failure = match attempt_once().await {
+                Ok(value) => return Ok((value, attempt)),
+                Err(failure) => failure,
+            };
+            if !failure.retryable || attempt >= self.retry.max_attempts {
+                return Err(FetchError { attempts: attempt, message: format!("{:#}", failure.error) }.into());
+            }
+            let delay = self.retry.delay_for(attempt, failure.retry_after);
+            if !quiet {
+                println!(
+                    "Attempt {}/{} for {} failed ({:#}); retrying in {:.1}s",
+                    attempt,
+                    self.retry.max_attempts,
+                    url,
+                    failure.error,
+                    delay.as_secs_f64()
+                );
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn post_once(&self, url: &str, headers: &HeaderMap, body: &str) -> Result<(), AttemptFailure> {
+        let response = self
+            .next_client()
+            .client
+            .post(url)
+            .headers(headers.clone())
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| AttemptFailure {
+                retryable: is_retryable_post_error(&e),
+                error: anyhow::Error::new(e).context("Failed to send request"),
+                retry_after: None,
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AttemptFailure {
+                error: anyhow::anyhow!("Request failed with status: {} for URL: {}", status, url),
+                retryable: is_retryable_status(status),
+                retry_after: parse_retry_after(response.headers()),
+            });
+        }
+        Ok(())
+    }
+
+    async fn fetch_once(&self, url: &str, quiet: bool) -> Result<String, AttemptFailure> {
+        let proxied = self.next_client();
+        if !quiet {
+            match &proxied.label {
+                Some(proxy) => println!("Fetching URL: {} via proxy {}", url, proxy),
+                None => println!("Fetching URL: {}", url),
+            }
+        }
+        let response = proxied.client.get(url).send().await.map_err(|e| AttemptFailure {
+            retryable: is_retryable_get_error(&e),
+            error: anyhow::Error::new(e).context("Failed to send request"),
+            retry_after: None,
+        })?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text().await.map_err(|e| AttemptFailure {
+            retryable: is_retryable_get_error(&e),
+            error: anyhow::Error::new(e).context("Failed to read response text"),
+            retry_after: None,
+        })?;
+
+        if let Some(cassette) = &self.record {
+            // Record before checking the status so failures replay the same way.
+            cassette
+                .record(url, status.as_u16(), &headers, &body)
+                .await
+                .map_err(|error| AttemptFailure { error, retryable: false, retry_after: None })?;
+            if !quiet {
+                println!("Recorded response for {} to {}", url, cassette.dir().display());
+            }
+        }
+
+        if !status.is_success() {
+            return Err(AttemptFailure {
+                error: anyhow::anyhow!("Request failed with status: {} for URL: {}", status, url),
+                retryable: is_retryable_status(status),
+                retry_after: parse_retry_after(&headers),
+            });
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            honor_retry_after: true,
+        }
+    }
+
+    fn retry_after(value: &str) -> Option<Duration> {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        parse_retry_after(&headers)
+    }
+
+    #[test]
+    fn backoff_doubles_within_jitter_bounds() {
+        let policy = policy();
+        for (attempt, full) in [(1, 100), (2, 200), (3, 400), (4, 800)] {
+            for _ in 0..50 {
+                let delay = policy.backoff(attempt);
+                let full = Duration::from_millis(full);
+                assert!(delay >= full / 2 && delay <= full, "attempt {}: {:?}", attempt, delay);
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_at_max_delay() {
+        let policy = policy();
+        for attempt in [5, 10, 40, u32::MAX] {
+            let delay = policy.backoff(attempt);
+            assert!(delay >= policy.max_delay / 2 && delay <= policy.max_delay, "attempt {}: {:?}", attempt, delay);
+        }
+    }
+
+    #[test]
+    fn delay_honors_retry_after_up_to_max_delay() {
+        let mut policy = policy();
+        assert_eq!(policy.delay_for(1, Some(Duration::from_millis(700))), Duration::from_millis(700));
+        assert_eq!(policy.delay_for(1, Some(Duration::from_secs(30))), policy.max_delay);
+        policy.honor_retry_after = false;
+        assert!(policy.delay_for(1, Some(Duration::from_secs(30))) <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(retry_after("-5"), None);
+        assert_eq!(retry_after("soon"), None);
+        assert_eq!(parse_retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn parses_retry_after_http_dates() {
+        let at = chrono::Utc::now() + chrono::Duration::seconds(90);
+        let wait = retry_after(&at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).unwrap();
+        assert!(wait > Duration::from_secs(80) && wait <= Duration::from_secs(90), "{:?}", wait);
+        // A date in the past means there's nothing to wait for.
+        assert_eq!(retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    /// Serves a raw HTTP stand-in that reads each request and drops the first
+    /// `drops` connections without answering, then replies 200 "ok". Returns
+    /// its URL and the number of connections accepted so far.
+    async fn flaky_server(drops: usize) -> (String, Arc<AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let connection = counter.fetch_add(1, Ordering::SeqCst);
+                let mut request = [0u8; 4096];
+                let _ = socket.read(&mut request).await;
+                if connection >= drops {
+                    let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok";
+                    let _ = socket.write_all(response.as_bytes()).await;
+                }
+            }
+        });
+        (url, accepted)
+    }
+
+    fn test_fetcher() -> Fetcher {
+        use clap::Parser;
+        let args = Args::try_parse_from(["gpu_pricecheck", "--retry-base-delay", "1"]).unwrap();
+        Fetcher::new(FetchConfig::from_args(&args)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_retries_a_dropped_connection() {
+        let (url, accepted) = flaky_server(1).await;
+        let page = test_fetcher().fetch_html(&url, true).await.unwrap();
+        assert_eq!((page.body.as_str(), page.attempts), ("ok", 2));
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn post_does_not_retry_a_dropped_connection() {
+        let (url, accepted) = flaky_server(1).await;
+        let error = test_fetcher().post(&url, &HeaderMap::new(), "{}", true).await.unwrap_err();
+        let attempts = error.downcast_ref::<FetchError>().map(|e| e.attempts);
+        assert_eq!(attempts, Some(1), "{:#}", error);
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn only_rate_limits_and_server_errors_are_retryable() {
+        for status in [StatusCode::TOO_MANY_REQUESTS, StatusCode::INTERNAL_SERVER_ERROR, StatusCode::BAD_GATEWAY] {
+            assert!(is_retryable_status(status), "{}", status);
+        }
+        for status in [StatusCode::BAD_REQUEST, StatusCode::NOT_FOUND, StatusCode::REQUEST_TIMEOUT] {
+            assert!(!is_retryable_status(status), "{}", status);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use crate::cli::GpuModel;
+use crate::scraper::{GpuListing, StockStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// A listing as parsed at a point in time, as stored in the history database.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub scraped_at: DateTime<Utc>,
+    pub model: GpuModel,
+    pub listing: GpuListing,
+}
+
+/// Where local state lives: `$XDG_DATA_HOME/gpu_pricecheck`, falling back to
+/// `~/.local/share` and then the current directory. As the XDG spec requires,
+/// an empty or relative `$XDG_DATA_HOME` is ignored.
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("gpu_pricecheck")
+}
+
+/// Default history database location: `history.db` in [`data_dir`].
+pub fn default_db_path() -> PathBuf {
+    data_dir().join("history.db")
+}
+
+/// The history database path from `--db`, or the default location.
+pub fn db_path(args: &crate::cli::Args) -> PathBuf {
+    args.db.clone().unwrap_or_else(default_db_path)
+}
+
+/// What changed for a listing between two observations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    FirstSeen,
+    StatusChange,
+    PriceChange,
+}
+
+/// One entry in a listing's timeline.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEvent {
+    pub time: DateTime<Utc>,
+    pub model: GpuModel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retailer: Option<String>,
+    pub name: String,
+    pub title: String,
+    pub link: String,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_status: Option<StockStatus>,
+    pub to_status: StockStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_price: Option<f64>,
+}
+
+impl HistoryEvent {
+    /// When the change was seen, in local time, e.g. "2026-10-16 20:38".
+    pub fn local_time(&self) -> String {
+        self.time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    /// Short description of the change, e.g. "Out of Stock → In Stock".
+    pub fn describe(&self) -> String {
+        let price = |p: Option<f64>| p.map_or("-".to_string(), |p| format!("${:.2}", p));
+        match self.kind {
+            ChangeKind::FirstSeen => format!("First seen: {} at {}", self.to_status, price(self.to_price)),
+            ChangeKind::StatusChange => format!(
+                "{} → {}",
+                self.from_status.as_ref().map_or("-".to_string(), ToString::to_string),
+                self.to_status
+            ),
+            ChangeKind::PriceChange => format!("{} → {}", price(self.from_price), price(self.to_price)),
+        }
+    }
+}
+
+/// Builds a per-listing timeline of status and price changes. Observations
+/// older than `since` are only used as the baseline for later changes.
+pub fn build_timeline(observations: &[Observation], since: Option<DateTime<Utc>>) -> Vec<HistoryEvent> {
+    let mut by_listing: BTreeMap<&str, Vec<&Observation>> = BTreeMap::new();
+    for observation in observations {
+        by_listing.entry(observation.listing.identity()).or_default().push(observation);
+    }
+    let mut events = Vec::new();
+    for mut history in by_listing.into_values() {
+        history.sort_by_key(|o| o.scraped_at);
+        let mut previous: Option<&Observation> = None;
+        for current in history {
+            let in_window = since.is_none_or(|since| current.scraped_at >= since);
+            // Each kind only carries what it's about: a status change has no
+            // prices, a price change no previous status.
+            let event = |kind, from: Option<&Observation>| {
+                let (from_status, from_price, to_price) = match kind {
+                    ChangeKind::FirstSeen => (None, None, current.listing.price_numeric),
+                    ChangeKind::StatusChange => (from.map(|o| o.listing.status.clone()), None, None),
+                    ChangeKind::PriceChange => {
+                        (None, from.and_then(|o| o.listing.price_numeric), current.listing.price_numeric)
+                    }
+                };
+                HistoryEvent {
+                    time: current.scraped_at,
+                    model: current.model,
+                    retailer: current.listing.retailer.clone(),
+                    name: current.listing.name.clone(),
+                    title: current.listing.title.clone(),
+                    link: current.listing.link.clone(),
+                    kind,
+                    from_status,
+                    to_status: current.listing.status.clone(),
+                    from_price,
+                    to_price,
+                }
+            };
+            if in_window {
+                match previous {
+                    None => events.push(event(ChangeKind::FirstSeen, None)),
+                    Some(prev) => {
+                        if prev.listing.status != current.listing.status {
+                            events.push(event(ChangeKind::StatusChange, Some(prev)));
+                        }
+                        if prev.listing.price_numeric != current.listing.price_numeric {
+                            events.push(event(ChangeKind::PriceChange, Some(prev)));
+                        }
+                    }
+                }
+            }
+            previous = Some(current);
+        }
+    }
+    events.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.name.cmp(&b.name)));
+    events
+}
+
+/// Price statistics across available listings for one scrape.
+#[derive(Debug, Clone, Serialize)]
+pub struct PricePoint {
+    pub time: DateTime<Utc>,
+    pub min: Option<f64>,
+    pub median: Option<f64>,
+    pub max: Option<f64>,
+    /// Number of available listings with a price in this scrape.
+    pub listings: usize,
+}
+
+/// A period during which a retailer had at least one listing in stock.
+#[derive(Debug, Clone, Serialize)]
+pub struct StockInterval {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// True when the retailer was still in stock at the last scrape.
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetailerAvailability {
+    pub retailer: String,
+    pub intervals: Vec<StockInterval>,
+}
+
+/// Chart data for a model's history page.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelHistory {
+    pub model: GpuModel,
+    pub prices: Vec<PricePoint>,
+    pub in_stock: Vec<RetailerAvailability>,
+}
+
+fn median(sorted: &[f64]) -> Option<f64> {
+    match sorted.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(sorted[n / 2]),
+        n => Some((sorted[n / 2 - 1] + sorted[n / 2]) / 2.0),
+    }
+}
+
+/// Price statistics over the available, priced listings of one scrape.
+pub fn price_point<'a>(time: DateTime<Utc>, listings: impl IntoIterator<Item = &'a GpuListing>) -> PricePoint {
+    let mut available: Vec<f64> = listings
+        .into_iter()
+        .filter(|l| l.status.is_available())
+        .filter_map(|l| l.price_numeric)
+        .collect();
+    available.sort_by(|a, b| a.total_cmp(b));
+    PricePoint {
+        time,
+        min: available.first().copied(),
+        median: median(&available),
+        max: available.last().copied(),
+        listings: available.len(),
+    }
+}
+
+/// Aggregates observations into per-scrape price statistics (over listings
+/// that are available and priced) and per-retailer in-stock intervals.
+pub fn model_history(model: GpuModel, observations: &[Observation], since: Option<DateTime<Utc>>) -> ModelHistory {
+    // Group by scrape, de-duplicating listings recorded more than once for the same scrape.
+    let mut scrapes: BTreeMap<DateTime<Utc>, BTreeMap<&str, &GpuListing>> = BTreeMap::new();
+    for observation in observations {
+        if since.is_some_and(|since| observation.scraped_at < since) {
+            continue;
+        }
+        scrapes
+            .entry(observation.scraped_at)
+            .or_default()
+            .insert(observation.listing.identity(), &observation.listing);
+    }
+
+    let prices = scrapes
+        .iter()
+        .map(|(time, listings)| price_point(*time, listings.values().copied()))
+        .collect();
+
+    let retailers: std::collections::BTreeSet<&str> = scrapes
+        .values()
+        .flat_map(|listings| listings.values().filter_map(|l| l.retailer.as_deref()))
+        .collect();
+    let last_scrape = scrapes.keys().next_back().copied();
+    let in_stock = retailers
+        .into_iter()
+        .map(|retailer| {
+            let mut intervals = Vec::new();
+            let mut started: Option<DateTime<Utc>> = None;
+            for (time, listings) in &scrapes {
+                let has_stock = listings.values().any(|l| {
+                    l.retailer.as_deref() == Some(retailer)
+                        && matches!(l.status, StockStatus::InStock | StockStatus::MarketplaceAvailable)
+                });
+                match (has_stock, started) {
+                    (true, None) => started = Some(*time),
+                    (false, Some(start)) => {
+                        intervals.push(StockInterval { start, end: *time, open: false });
+                        started = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let (Some(start), Some(end)) = (started, last_scrape) {
+                intervals.push(StockInterval { start, end, open: true });
+            }
+            RetailerAvailability { retailer: retailer.to_string(), intervals }
+        })
+        .collect();
+
+    ModelHistory { model, prices, in_stock }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn observation(hours: i64, link: &str, status: StockStatus, price: Option<f64>) -> Observation {
+        Observation {
+            scraped_at: at(hours),
+            model: GpuModel::Rtx5090,
+            listing: GpuListing::for_test(link, status, price),
+        }
+    }
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 6, 15, 12, 0, 0).unwrap() + chrono::Duration::hours(hours)
+    }
+
+    fn kinds(events: &[HistoryEvent]) -> Vec<(DateTime<Utc>, ChangeKind)> {
+        events.iter().map(|e| (e.time, e.kind)).collect()
+    }
+
+    #[test]
+    fn first_sighting_is_an_added_event() {
+        let events = build_timeline(&[observation(0, "a", StockStatus::InStock, Some(1999.0))], None);
+        assert_eq!(kinds(&events), vec![(at(0), ChangeKind::FirstSeen)]);
+        let event = &events[0];
+        assert_eq!((event.from_status.as_ref(), &event.to_status), (None, &StockStatus::InStock));
+        assert_eq!((event.from_price, event.to_price), (None, Some(1999.0)));
+        assert_eq!(event.describe(), "First seen: In Stock at $1999.00");
+    }
+
+    #[test]
+    fn status_changes_carry_no_prices() {
+        let events = build_timeline(
+            &[
+                observation(0, "a", StockStatus::OutOfStock, Some(1999.0)),
+                observation(1, "a", StockStatus::InStock, Some(1999.0)),
+            ],
+            None,
+        );
+        assert_eq!(kinds(&events), vec![(at(0), ChangeKind::FirstSeen), (at(1), ChangeKind::StatusChange)]);
+        let change = &events[1];
+        assert_eq!(change.from_status, Some(StockStatus::OutOfStock));
+        assert_eq!(change.to_status, StockStatus::InStock);
+        assert_eq!((change.from_price, change.to_price), (None, None));
+        assert_eq!(change.describe(), "Out of Stock → In Stock");
+    }
+
+    #[test]
+    fn price_changes_carry_no_previous_status() {
+        let events = build_timeline(
+            &[
+                observation(0, "a", StockStatus::InStock, Some(1999.0)),
+                observation(1, "a", StockStatus::InStock, Some(1999.0)),
+                observation(2, "a", StockStatus::InStock, Some(1899.0)),
+            ],
+            None,
+        );
+        assert_eq!(kinds(&events), vec![(at(0), ChangeKind::FirstSeen), (at(2), ChangeKind::PriceChange)]);
+        let change = &events[1];
+        assert_eq!(change.from_status, None);
+        assert_eq!((change.from_price, change.to_price), (Some(1999.0), Some(1899.0)));
+        assert_eq!(change.describe(), "$1999.00 → $1899.00");
+    }
+
+    #[test]
+    fn status_and_price_change_together_are_separate_events() {
+        let events = build_timeline(
+            &[
+                observation(0, "a", StockStatus::OutOfStock, None),
+                observation(1, "a", StockStatus::InStock, Some(1999.0)),
+            ],
+            None,
+        );
+        assert_eq!(
+            kinds(&events),
+            vec![(at(0), ChangeKind::FirstSeen), (at(1), ChangeKind::StatusChange), (at(1), ChangeKind::PriceChange)]
+        );
+        assert_eq!((events[2].from_price, events[2].to_price), (None, Some(1999.0)));
+    }
+
+    #[test]
+    fn removed_listings_end_their_timeline() {
+        let events = build_timeline(
+            &[
+                observation(0, "a", StockStatus::InStock, Some(1999.0)),
+                observation(0, "b", StockStatus::InStock, Some(2099.0)),
+                observation(1, "b", StockStatus::InStock, Some(2099.0)),
+                observation(2, "b", StockStatus::OutOfStock, Some(2099.0)),
+            ],
+            None,
+        );
+        // `a` isn't in later scrapes: it has no events after it was first seen.
+        let a: Vec<_> = events.iter().filter(|e| e.link.ends_with("/a")).map(|e| e.kind).collect();
+        assert_eq!(a, vec![ChangeKind::FirstSeen]);
+        assert_eq!(
+            kinds(&events),
+            vec![(at(0), ChangeKind::FirstSeen), (at(0), ChangeKind::FirstSeen), (at(2), ChangeKind::StatusChange)]
+        );
+    }
+
+    #[test]
+    fn since_uses_earlier_observations_as_the_baseline() {
+        let observations = [
+            observation(0, "a", StockStatus::OutOfStock, Some(1999.0)),
+            observation(1, "a", StockStatus::OutOfStock, Some(1999.0)),
+            observation(2, "a", StockStatus::InStock, Some(1899.0)),
+            observation(3, "b", StockStatus::InStock, Some(2099.0)),
+        ];
+        let events = build_timeline(&observations, Some(at(2)));
+        // The baseline row itself isn't shown; the first change in the window is
+        // relative to it rather than a first sighting.
+        assert_eq!(
+            kinds(&events),
+            vec![(at(2), ChangeKind::StatusChange), (at(2), ChangeKind::PriceChange), (at(3), ChangeKind::FirstSeen)]
+        );
+        assert_eq!(events[0].from_status, Some(StockStatus::OutOfStock));
+        assert_eq!(events[1].from_price, Some(1999.0));
+
+        // Nothing changed in the window: no events.
+        assert!(build_timeline(&observations[..2], Some(at(1))).is_empty());
+    }
+}
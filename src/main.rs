@@ -2,35 +2,266 @@ use anyhow::{Context, Result};
 use clap::{Parser, ValueEnum}; // Import ValueEnum trait
 
 // Declare modules
+mod alerts;
+mod cache;
+mod cassette;
 mod cli;
+mod diff;
+mod email;
+mod fetcher;
+mod history;
+mod notify;
 mod output;
+mod poller;
+mod recorder;
 mod scraper;
+mod storage;
+mod template;
 mod web; // Add web module
 
 // Use items from modules
-use cli::{Args, GpuModel, OutputFormat, SortColumn};
-use scraper::GpuListing; // Keep GpuListing import
+use alerts::{AlertEngine, AlertEvent};
+use cli::{Args, Command, EmailDigestArgs, GpuModel, HistoryArgs, OutputFormat, SortColumn};
+use diff::ListingChange;
+use fetcher::{FetchConfig, Fetcher, ModelFetchReport};
+use output::{ChangeRecord, Delimiter, ListingRecord, ReportBody, TableLayout};
+use recorder::ScrapeRecorder;
+use scraper::{GpuListing, StockStatus}; // Keep GpuListing import
+use storage::Storage;
+use std::collections::HashSet;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Process exit codes, so scripts and cron jobs can react without parsing output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// At least one listing (or change, with `--diff`) was found.
+    Matches = 0,
+    /// Every page loaded, but nothing matched.
+    NoMatches = 1,
+    /// Some models failed to load, and either nothing matched or `--fail-on-partial` is set.
+    PartialFailure = 2,
+    /// Every model failed, or the run failed outright (bad arguments, parse errors, ...).
+    Failure = 3,
+}
+
+impl From<Outcome> for ExitCode {
+    fn from(outcome: Outcome) -> Self {
+        ExitCode::from(outcome as u8)
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     // Make Args mutable so we can override defaults when no extra parameters are given.
-    let mut args = Args::parse();
+    let mut args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = e.print();
+            // --help and --version aren't failures.
+            return if e.use_stderr() { Outcome::Failure.into() } else { ExitCode::SUCCESS };
+        }
+    };
     // If only the program name is provided, set cheapest_each to true.
     if std::env::args().len() == 1 {
         args.cheapest_each = true;
     }
-    if args.web {
-        web::run_server(args.listen).await?;
+    match run(args).await {
+        Ok(outcome) => outcome.into(),
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            Outcome::Failure.into()
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<Outcome> {
+    if let Some(Command::History(history_args)) = &args.command {
+        run_history(&args, history_args)?;
+    } else if let Some(Command::TestNotify) = &args.command {
+        run_test_notify(&args).await?;
+    } else if let Some(Command::EmailDigest(digest_args)) = &args.command {
+        run_email_digest(&args, digest_args).await?;
+    } else if args.web {
+        let fetcher = Fetcher::new(FetchConfig::from_args(&args))?;
+        let source = web::HtmlSource::from_args(&args);
+        let storage = history_storage(&args);
+        let recorder = ScrapeRecorder::from_args(&args, &source, &storage);
+        let alerts = AlertEngine::from_args(&args, &fetcher, &storage)?;
+        // Runs until the server stops.
+        let _digest = email::spawn_daily_digest(&args, &storage)?;
+        web::run_server(web::ServerConfig::from_args(&args), fetcher, source, storage, recorder, alerts).await?;
     } else {
-        run_cli(args).await?;
+        return run_cli(args).await;
     }
-    Ok(())
+    Ok(Outcome::Matches)
+}
+
+/// The history database (`--db`, or the default location), opened on first use.
+fn history_storage(args: &Args) -> Arc<Storage> {
+    Arc::new(Storage::new(history::db_path(args)))
+}
+
+/// What the CLI needs to run a check, kept across `--watch` iterations so
+/// connections and settings are reused.
+struct CliContext {
+    fetcher: Fetcher,
+    source: web::HtmlSource,
+    storage: Arc<Storage>,
+    /// Every recorded scrape becomes the baseline for the next `--diff`.
+    recorder: ScrapeRecorder,
+    /// Keeps rule state between `--watch` iterations.
+    alerts: AlertEngine,
+    /// `--template`, loaded up front so mistakes show before any fetching.
+    template: Option<template::UserTemplate>,
+}
+
+/// The outcome of one check, ready to print.
+struct CheckResult {
+    listings: Vec<GpuListing>,
+    /// The model page each of `listings` came from and when it was fetched, in
+    /// the same order. With `--diff`, the page the changes were found on.
+    sources: Vec<(GpuModel, chrono::DateTime<chrono::Local>)>,
+    /// Set with `--diff`, and printed instead of `listings`.
+    changes: Option<Vec<ListingChange>>,
+    fetch_reports: Vec<ModelFetchReport>,
+    /// Alert rules that fired on this check's pages.
+    alerts: Vec<AlertEvent>,
+}
+
+impl CheckResult {
+    /// When the oldest page behind this result was fetched (now, if unknown).
+    fn checked_at(&self) -> chrono::DateTime<chrono::Local> {
+        self.sources.iter().map(|(_, fetched_at)| *fetched_at).min().unwrap_or_else(chrono::Local::now)
+    }
+
+    /// Listings with their model and scrape time, for the line-oriented formats.
+    fn records(&self) -> Vec<ListingRecord<'_>> {
+        self.listings
+            .iter()
+            .zip(&self.sources)
+            .map(|(listing, &(model, fetched_at))| ListingRecord { model, scraped_at: fetched_at.into(), listing })
+            .collect()
+    }
+
+    /// `--diff` changes with the scrape time of the page they were found on.
+    fn change_records(&self) -> Vec<ChangeRecord<'_>> {
+        let scraped_at = self.checked_at().into();
+        self.changes.iter().flatten().map(|change| ChangeRecord { scraped_at, change }).collect()
+    }
+
+    fn outcome(&self, fail_on_partial: bool) -> Outcome {
+        let failed = self.fetch_reports.iter().filter(|r| r.error.is_some()).count();
+        let matched = match &self.changes {
+            Some(changes) => !changes.is_empty(),
+            None => !self.listings.is_empty(),
+        };
+        if failed > 0 && failed == self.fetch_reports.len() {
+            Outcome::Failure
+        } else if failed > 0 && (fail_on_partial || !matched) {
+            // With nothing matched, a failed model may have been the one in stock.
+            Outcome::PartialFailure
+        } else if matched {
+            Outcome::Matches
+        } else {
+            Outcome::NoMatches
+        }
+    }
+}
+
+async fn run_cli(args: Args) -> Result<Outcome> {
+    let source = web::HtmlSource::from_args(&args);
+    let fetcher = Fetcher::new(FetchConfig::from_args(&args))?;
+    let storage = history_storage(&args);
+    let ctx = CliContext {
+        alerts: AlertEngine::from_args(&args, &fetcher, &storage)?,
+        fetcher,
+        recorder: ScrapeRecorder::from_args(&args, &source, &storage),
+        storage,
+        template: args.template.as_deref().map(template::UserTemplate::load).transpose()?,
+        source,
+    };
+    if args.diff && !ctx.recorder.is_enabled() {
+        eprintln!(
+            "Warning: --diff compares against the latest recorded scrape, but this run isn't recorded \
+             (--no-history or saved pages); the baseline may be stale or missing and won't move."
+        );
+    }
+    let outcome = match args.watch {
+        Some(interval) => run_watch(&args, &ctx, interval).await,
+        None => run_check(&args, &ctx).await.and_then(|result| {
+            print_check(&args, &result, &HashSet::new(), ctx.template.as_ref())?;
+            Ok(result.outcome(args.fail_on_partial))
+        }),
+    };
+    // Alerts are delivered in the background; don't exit before they're out.
+    // Ctrl-C skips the wait.
+    tokio::select! {
+        _ = ctx.alerts.flush() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    outcome
 }
 
-async fn run_cli(args: Args) -> Result<()> {
+/// Re-runs the check every `interval` until Ctrl-C. Tables are redrawn in
+/// place with the rows that changed since the previous iteration highlighted;
+/// other formats print one result after another. Returns the outcome of the
+/// last completed check.
+async fn run_watch(args: &Args, ctx: &CliContext, interval: Duration) -> Result<Outcome> {
+    let redraw = args.format == OutputFormat::Table;
+    let mut ctrl_c = std::pin::pin!(tokio::signal::ctrl_c());
+    let mut previous: Option<Vec<GpuListing>> = None;
+    let mut outcome = Outcome::NoMatches;
+    let _digest = email::spawn_daily_digest(args, &ctx.storage)?;
+    loop {
+        let check = tokio::select! {
+            result = run_check(args, ctx) => result,
+            _ = &mut ctrl_c => break,
+        };
+        if redraw {
+            // Clear the screen and move the cursor home.
+            print!("\x1b[2J\x1b[H");
+            println!(
+                "Every {}s: {} (last checked {}; Ctrl-C to stop)\n",
+                interval.as_secs(),
+                if args.cheapest_each { "cheapest of each model".to_string() } else { args.gpu.cli_name().to_string() },
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        match check {
+            Ok(result) => {
+                let changed = previous
+                    .as_ref()
+                    .map(|previous| diff::changed_identities(previous, &result.listings))
+                    .unwrap_or_default();
+                print_check(args, &result, &changed, ctx.template.as_ref())?;
+                outcome = result.outcome(args.fail_on_partial);
+                previous = Some(result.listings);
+            }
+            // Keep watching through failed checks; the next one may succeed.
+            Err(e) => {
+                eprintln!("Check failed: {:#}", e);
+                outcome = Outcome::Failure;
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = &mut ctrl_c => break,
+        }
+    }
+    println!();
+    Ok(outcome)
+}
+
+/// Fetches, parses, records and filters listings for the requested model (or
+/// the cheapest of each model), sorted and limited for display.
+async fn run_check(args: &Args, ctx: &CliContext) -> Result<CheckResult> {
     // Use the verbose flag to control logging
     let logging = args.verbose;
-    let mut final_listings: Vec<GpuListing> = Vec::new();
+    let mut final_listings: Vec<((GpuModel, chrono::DateTime<chrono::Local>), GpuListing)> = Vec::new();
+    let mut fetch_reports: Vec<ModelFetchReport> = Vec::new();
+    let mut alerts: Vec<AlertEvent> = Vec::new();
 
     if args.cheapest_each {
         if logging {
@@ -41,23 +272,25 @@ async fn run_cli(args: Args) -> Result<()> {
         let cheapest_futures = models.iter().map(|model| {
             let model = *model;
             async move {
-                let model_url = format!("{}{}", scraper::get_base_url(model), model);
-                let res = (|| async {
-                    let html = web::fetch_html(&model_url, !logging)
+                let mut attempts = 1;
+                let res = async {
+                    let page = ctx.source.load(&ctx.fetcher, model, !logging)
                         .await
                         .with_context(|| format!("Failed to fetch HTML for {:?}", model))?;
-                    let mut listings = scraper::parse_listings(&html, !logging)
+                    attempts = page.attempts;
+                    let mut listings = scraper::parse_listings(&page.body, !logging)
                         .with_context(|| format!("Failed to parse listings for {:?}", model))?;
+                    // A failed write shouldn't count as a failed fetch.
+                    if let Err(e) = ctx.recorder.record(model, &listings, page.fetched_at).await {
+                        eprintln!("Failed to record observations for {:?}: {:#}", model, e);
+                    }
+                    let fired = ctx.alerts.check(model, &listings, page.fetched_at);
                     if !args.all {
-                        listings.retain(|item| {
-                            let lower_status = item.status.to_lowercase();
-                            lower_status != "out of stock" && lower_status != "not tracking"
-                        });
+                        listings.retain(|item| item.status.is_available());
                     }
+                    apply_listing_filters(&mut listings, args);
                     // Remove "Preorder" listings so that only in-stock items are considered for cheapest_each
-                    listings = listings.into_iter()
-                        .filter(|listing| listing.status.to_lowercase() != "preorder")
-                        .collect();
+                    listings.retain(|listing| listing.status != StockStatus::Preorder);
                     let cheapest = listings.into_iter()
                         .filter(|item| item.price_numeric.is_some())
                         .min_by(|a, b| {
@@ -65,15 +298,24 @@ async fn run_cli(args: Args) -> Result<()> {
                                 .partial_cmp(&b.price_numeric.unwrap())
                                 .unwrap_or(std::cmp::Ordering::Equal)
                         });
-                    Ok::<Option<GpuListing>, anyhow::Error>(cheapest)
-                })().await;
-                (model, res)
+                    let cheapest = cheapest.map(|listing| (page.fetched_at, listing));
+                    Ok::<_, anyhow::Error>((cheapest, fired))
+                }.await;
+                let report = match &res {
+                    Ok(_) => ModelFetchReport::success(model, attempts),
+                    Err(e) => ModelFetchReport::failure(model, e),
+                };
+                (model, res, report)
             }
         });
         let results = futures::future::join_all(cheapest_futures).await;
-        for (model, res) in results {
-            match res {
-                Ok(Some(listing)) => final_listings.push(listing),
+        for (model, res, report) in results {
+            fetch_reports.push(report);
+            if let Ok((_, fired)) = &res {
+                alerts.extend(fired.iter().cloned());
+            }
+            match res.map(|(cheapest, _)| cheapest) {
+                Ok(Some((fetched_at, listing))) => final_listings.push(((model, fetched_at), listing)),
                 Ok(None) if logging => {
                     println!("No available listing with a valid price found for {:?}", model);
                 },
@@ -84,15 +326,32 @@ async fn run_cli(args: Args) -> Result<()> {
             }
         }
     } else {
-        let url = format!("{}{}", scraper::get_base_url(args.gpu), args.gpu);
-        let html = web::fetch_html(&url, !logging).await?;
-        let mut listings = scraper::parse_listings(&html, !logging)?;
+        let page = ctx.source.load(&ctx.fetcher, args.gpu, !logging).await?;
+        fetch_reports.push(ModelFetchReport::success(args.gpu, page.attempts));
+        let mut listings = scraper::parse_listings(&page.body, !logging)?;
+        // Read the baseline before this scrape is recorded and becomes the latest.
+        let previous = if args.diff { previous_scrape(&ctx.storage, args.gpu).await? } else { None };
+        match ctx.recorder.record(args.gpu, &listings, page.fetched_at).await {
+            Ok(recorded) if logging && ctx.recorder.is_enabled() => {
+                println!("Recorded {} observations to {}", recorded, history::db_path(args).display());
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to record observations for {:?}: {:#}", args.gpu, e),
+        }
+        alerts = ctx.alerts.check(args.gpu, &listings, page.fetched_at);
+        if args.diff {
+            let changes = diff_against(args, previous, listings, ctx.recorder.is_enabled());
+            return Ok(CheckResult {
+                listings: Vec::new(),
+                sources: vec![(args.gpu, page.fetched_at)],
+                changes: Some(changes),
+                fetch_reports,
+                alerts,
+            });
+        }
         if !args.all {
             let original_count = listings.len();
-            listings.retain(|item| {
-                let lower_status = item.status.to_lowercase();
-                lower_status != "out of stock" && lower_status != "not tracking"
-            });
+            listings.retain(|item| item.status.is_available());
             let filtered_count = listings.len();
             if logging && original_count > filtered_count {
                 println!(
@@ -103,7 +362,15 @@ async fn run_cli(args: Args) -> Result<()> {
         } else if logging {
             println!("Showing all listings (--all flag detected).");
         }
-        final_listings = listings;
+        let before_filters = listings.len();
+        apply_listing_filters(&mut listings, args);
+        if logging && before_filters > listings.len() {
+            println!(
+                "Filtered out {} listings not matching the requested filters.",
+                before_filters - listings.len()
+            );
+        }
+        final_listings = listings.into_iter().map(|listing| ((args.gpu, page.fetched_at), listing)).collect();
     }
 
     if !final_listings.is_empty() && logging {
@@ -113,21 +380,7 @@ async fn run_cli(args: Args) -> Result<()> {
             if args.desc { "descending" } else { "ascending" }
         );
     }
-    final_listings.sort_by(|a, b| {
-        let ordering = match args.sort_by {
-            SortColumn::Name => a.name.cmp(&b.name),
-            SortColumn::Status => a.status.cmp(&b.status),
-            SortColumn::Price => match (a.price_numeric, b.price_numeric) {
-                (Some(pa), Some(pb)) => pa.partial_cmp(&pb).unwrap_or(std::cmp::Ordering::Equal),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => a.price.cmp(&b.price),
-            },
-            SortColumn::LastAvailable => a.last_available.cmp(&b.last_available),
-            SortColumn::Link => a.link.cmp(&b.link),
-        };
-        if args.desc { ordering.reverse() } else { ordering }
-    });
+    final_listings.sort_by(|(_, a), (_, b)| compare_listings(a, b, args));
 
     if let Some(limit) = args.limit {
         if limit < final_listings.len() && logging {
@@ -136,11 +389,224 @@ async fn run_cli(args: Args) -> Result<()> {
         final_listings.truncate(limit);
     }
 
-    match args.format {
-        OutputFormat::Table => output::print_table(&final_listings, &args.sort_by, args.desc),
-        OutputFormat::Json => output::print_json(&final_listings)?,
-        OutputFormat::Yaml => output::print_yaml(&final_listings)?,
-        OutputFormat::Toml => output::print_toml(&final_listings)?,
+    let (sources, listings) = final_listings.into_iter().unzip();
+    Ok(CheckResult { listings, sources, changes: None, fetch_reports, alerts })
+}
+
+/// Prints a check's listings (or changes, with `--diff`) in the requested
+/// format (or `--template`), followed by any fetch issues. `changed` rows are
+/// highlighted in tables.
+fn print_check(
+    args: &Args,
+    result: &CheckResult,
+    changed: &HashSet<&str>,
+    template: Option<&template::UserTemplate>,
+) -> Result<()> {
+    if let Some(changes) = &result.changes {
+        match args.format {
+            OutputFormat::Table => output::print_diff_table(changes),
+            OutputFormat::Json => output::print_json("changes", changes)?,
+            OutputFormat::Yaml => output::print_yaml("changes", changes)?,
+            OutputFormat::Toml => output::print_toml("changes", changes)?,
+            OutputFormat::Csv => output::print_delimited(&result.change_records(), Delimiter::Comma)?,
+            OutputFormat::Tsv => output::print_delimited(&result.change_records(), Delimiter::Tab)?,
+            OutputFormat::Ndjson => output::print_ndjson(&result.change_records())?,
+            OutputFormat::Markdown => output::print_markdown_changes(changes)?,
+            OutputFormat::Html => output::print_html_report(
+                &format!("{:?} Changes Since the Previous Run", args.gpu),
+                Some(result.checked_at()),
+                &result.fetch_reports,
+                ReportBody::Changes(changes),
+            )?,
+        }
+    } else if let Some(template) = template {
+        output::print_template(&result.records(), template)?;
+    } else {
+        let listings = &result.listings;
+        match args.format {
+            OutputFormat::Table => output::print_table(
+                &result.records(),
+                &args.sort_by,
+                args.desc,
+                changed,
+                &TableLayout::from_args(args),
+            ),
+            OutputFormat::Json => output::print_json("listings", listings)?,
+            OutputFormat::Yaml => output::print_yaml("listings", listings)?,
+            OutputFormat::Toml => output::print_toml("listings", listings)?,
+            OutputFormat::Csv => output::print_delimited(&result.records(), Delimiter::Comma)?,
+            OutputFormat::Tsv => output::print_delimited(&result.records(), Delimiter::Tab)?,
+            OutputFormat::Ndjson => output::print_ndjson(&result.records())?,
+            OutputFormat::Markdown => output::print_markdown_listings(&result.records())?,
+            OutputFormat::Html => output::print_html_report(
+                &if args.cheapest_each {
+                    "Cheapest Available Listing per Model".to_string()
+                } else {
+                    format!("{:?} Listings", args.gpu)
+                },
+                Some(result.checked_at()),
+                &result.fetch_reports,
+                ReportBody::Listings(listings),
+            )?,
+        }
+    }
+    output::print_fetch_report(&result.fetch_reports);
+    output::print_alerts(&result.alerts);
+    Ok(())
+}
+
+/// Orders two listings by `--sort-by`/`--desc`.
+fn compare_listings(a: &GpuListing, b: &GpuListing, args: &Args) -> std::cmp::Ordering {
+    let ordering = match args.sort_by {
+        SortColumn::Name => a.name.cmp(&b.name),
+        SortColumn::Retailer => a.retailer.cmp(&b.retailer).then_with(|| a.title.cmp(&b.title)),
+        // Alphabetical by the status text shown, not enum order.
+        SortColumn::Status => a.status.to_string().cmp(&b.status.to_string()),
+        SortColumn::Price => match (a.price_numeric, b.price_numeric) {
+            (Some(pa), Some(pb)) => pa.partial_cmp(&pb).unwrap_or(std::cmp::Ordering::Equal),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a.price.cmp(&b.price),
+        },
+        SortColumn::LastAvailable => a.last_available.cmp(&b.last_available),
+        SortColumn::Link => a.link.cmp(&b.link),
+    };
+    if args.desc { ordering.reverse() } else { ordering }
+}
+
+/// The latest recorded scrape of `model`, the baseline for `--diff`.
+async fn previous_scrape(
+    storage: &Arc<Storage>,
+    model: GpuModel,
+) -> Result<Option<(chrono::DateTime<chrono::Utc>, Vec<GpuListing>)>> {
+    let storage = storage.clone();
+    tokio::task::spawn_blocking(move || storage.latest_scrape(model))
+        .await
+        .context("History database reader panicked")?
+}
+
+/// Compares the current listings against the previous recorded scrape of
+/// `args.gpu`. The listing filters apply to both sides, but unavailable
+/// listings are kept so that stock transitions show up.
+fn diff_against(
+    args: &Args,
+    previous: Option<(chrono::DateTime<chrono::Utc>, Vec<GpuListing>)>,
+    mut current: Vec<GpuListing>,
+    recorded: bool,
+) -> Vec<ListingChange> {
+    let mut changes = match previous {
+        Some((scraped_at, mut previous)) => {
+            if args.verbose {
+                println!(
+                    "Comparing against the scrape from {}",
+                    scraped_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S")
+                );
+            }
+            apply_listing_filters(&mut previous, args);
+            apply_listing_filters(&mut current, args);
+            diff::diff_listings(args.gpu, &previous, &current)
+        }
+        None => {
+            eprintln!(
+                "No recorded scrape of {} to compare against{}",
+                args.gpu.cli_name(),
+                if recorded { "; this run is now the baseline." } else { "." }
+            );
+            Vec::new()
+        }
+    };
+    changes.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| compare_listings(&a.listing, &b.listing, args)));
+    if let Some(limit) = args.limit {
+        changes.truncate(limit);
+    }
+    changes
+}
+
+/// Prints the timeline of changes recorded in the history database.
+fn run_history(args: &Args, history_args: &HistoryArgs) -> Result<()> {
+    let storage = history_storage(args);
+    let since = history_args.since.map(|window| chrono::Utc::now() - window);
+    let mut observations = match since {
+        Some(since) => storage.observations_with_baseline(Some(history_args.gpu), since)?,
+        None => storage.observations(Some(history_args.gpu), None)?,
+    };
+    if !history_args.retailer.is_empty() {
+        let retailers: Vec<String> = history_args.retailer.iter().map(|r| scraper::name_key(r)).collect();
+        observations.retain(|o| {
+            o.listing.retailer.as_deref().is_some_and(|r| retailers.contains(&scraper::name_key(r)))
+        });
+    }
+    let events = history::build_timeline(&observations, since);
+    match history_args.format {
+        OutputFormat::Table => output::print_history_table(&events),
+        OutputFormat::Json => output::print_json("events", &events)?,
+        OutputFormat::Yaml => output::print_yaml("events", &events)?,
+        OutputFormat::Toml => output::print_toml("events", &events)?,
+        OutputFormat::Csv => output::print_delimited(&events, Delimiter::Comma)?,
+        OutputFormat::Tsv => output::print_delimited(&events, Delimiter::Tab)?,
+        OutputFormat::Ndjson => output::print_ndjson(&events)?,
+        OutputFormat::Markdown => output::print_markdown_history(&events)?,
+        OutputFormat::Html => output::print_html_report(
+            &format!("{:?} History", history_args.gpu),
+            Some(chrono::Local::now()),
+            &[],
+            ReportBody::History(&events),
+        )?,
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Sends a sample alert through the configured notifiers, failing if any delivery fails.
+async fn run_test_notify(args: &Args) -> Result<()> {
+    let fetcher = Fetcher::new(FetchConfig::from_args(args))?;
+    let notifier = notify::Notifier::from_args(args, &fetcher)?;
+    if notifier.is_empty() {
+        anyhow::bail!(
+            "No notifiers configured (use --webhook, --discord-webhook, --slack-webhook, --ntfy or --email-to)"
+        );
+    }
+    notifier.send(&[notify::sample_event()]).await?;
+    println!("Test notification sent.");
+    Ok(())
+}
+
+/// Sends (or with `--dry-run`, prints) the digest email right away.
+async fn run_email_digest(args: &Args, digest_args: &EmailDigestArgs) -> Result<()> {
+    let storage = history_storage(args);
+    let since = digest_args.since;
+    let digest = tokio::task::spawn_blocking(move || email::Digest::build(&storage, since)).await??;
+    if digest_args.dry_run {
+        print!("{}", digest.render()?.0);
+        return Ok(());
+    }
+    let Some(mailer) = email::Mailer::from_args(args)? else {
+        anyhow::bail!("No email configured (use --smtp-host and --email-to)");
+    };
+    mailer.send_digest(&digest).await?;
+    println!("Digest sent.");
+    Ok(())
+}
+
+/// Applies the user-requested listing filters (retailer, brand, variant) in place.
+fn apply_listing_filters(listings: &mut Vec<GpuListing>, args: &Args) {
+    let included: Vec<String> = args.retailer.iter().map(|r| scraper::name_key(r)).collect();
+    let excluded: Vec<String> = args.exclude_retailer.iter().map(|r| scraper::name_key(r)).collect();
+    let brands: Vec<String> = args.brand.iter().map(|b| scraper::name_key(b)).collect();
+    if included.is_empty() && excluded.is_empty() && brands.is_empty() && args.exclude_variant.is_empty() {
+        return;
+    }
+    listings.retain(|item| {
+        let key = item.retailer.as_deref().map(scraper::name_key);
+        let is_included = included.is_empty()
+            || key.as_ref().is_some_and(|k| included.contains(k));
+        let is_excluded = key.as_ref().is_some_and(|k| excluded.contains(k));
+        let brand_key = item.product.as_ref()
+            .and_then(|p| p.brand.as_deref())
+            .map(scraper::name_key);
+        let brand_matches = brands.is_empty()
+            || brand_key.is_some_and(|k| brands.contains(&k));
+        let has_excluded_variant = item.product.as_ref()
+            .is_some_and(|p| args.exclude_variant.iter().any(|v| p.has_variant(*v)));
+        is_included && !is_excluded && brand_matches && !has_excluded_variant
+    });
+}
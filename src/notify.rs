@@ -0,0 +1,518 @@
+use crate::alerts::AlertEvent;
+use crate::cli::{Args, GpuModel};
+use crate::email::Mailer;
+use crate::fetcher::Fetcher;
+use crate::output;
+use crate::scraper::{GpuListing, ProductInfo, StockStatus};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use comfy_table::Color;
+use futures::future::join_all;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::Serialize;
+use serde_json::json;
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// Header carrying `sha256=<hex HMAC of the body>` when `--webhook-secret` is set.
+pub const SIGNATURE_HEADER: &str = "X-Gpu-Pricecheck-Signature";
+
+/// One listing from a fired alert, as posted to webhooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub rule: String,
+    pub message: String,
+    pub model: GpuModel,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retailer: Option<String>,
+    pub status: StockStatus,
+    pub price: Option<f64>,
+    pub link: String,
+    pub timestamp: DateTime<Utc>,
+    /// The price as shown on the site, for the chat formatters.
+    #[serde(skip)]
+    pub price_display: String,
+}
+
+impl Notification {
+    /// One notification per listing of each event.
+    pub fn from_events(events: &[AlertEvent]) -> Vec<Notification> {
+        events
+            .iter()
+            .flat_map(|event| {
+                event.listings.iter().map(move |listing| Notification {
+                    rule: event.rule.clone(),
+                    message: event.message.clone(),
+                    model: event.model,
+                    name: listing.title.clone(),
+                    retailer: listing.retailer.clone(),
+                    status: listing.status.clone(),
+                    price: listing.price_numeric,
+                    link: listing.link.clone(),
+                    timestamp: event.fired_at,
+                    price_display: listing.price.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn retailer_display(&self) -> &str {
+        self.retailer.as_deref().unwrap_or("-")
+    }
+
+    /// e.g. "5090 In Stock: NVIDIA GeForce RTX 5090 Founders Edition"
+    fn headline(&self) -> String {
+        format!("{} {}: {}", self.model.cli_name(), self.status, self.name)
+    }
+}
+
+/// The status color from the CLI table as RGB, with the web UI's shades.
+fn status_rgb(status: &StockStatus) -> u32 {
+    match output::status_color(status) {
+        Some(Color::Green) => 0x28a745,
+        Some(Color::Yellow) => 0xffc107,
+        Some(Color::Red) => 0xdc3545,
+        Some(Color::DarkGreen) => 0x1e7e34,
+        _ => 0x6c757d,
+    }
+}
+
+/// ntfy has no colors, so the status color is shown as a colored circle tag.
+fn status_emoji_tag(status: &StockStatus) -> &'static str {
+    match output::status_color(status) {
+        Some(Color::Green) | Some(Color::DarkGreen) => "green_circle",
+        Some(Color::Yellow) => "yellow_circle",
+        Some(Color::Red) => "red_circle",
+        _ => "white_circle",
+    }
+}
+
+/// Where notifications go and how they're formatted.
+enum Target {
+    /// Generic JSON webhook (`--webhook`), with optional extra headers and HMAC signature.
+    Webhook { url: String, headers: HeaderMap, secret: Option<String> },
+    /// Discord webhook, one embed per listing.
+    Discord { url: String },
+    /// Slack incoming webhook, Block Kit message in a colored attachment.
+    Slack { url: String },
+    /// ntfy topic URL (e.g. https://ntfy.sh/my-topic).
+    Ntfy { url: String, token: Option<String> },
+}
+
+impl Target {
+    fn url(&self) -> &str {
+        match self {
+            Target::Webhook { url, .. } | Target::Discord { url } | Target::Slack { url } | Target::Ntfy { url, .. } => url,
+        }
+    }
+
+    /// Builds the request headers and body for one notification.
+    fn request(&self, n: &Notification) -> Result<(HeaderMap, String)> {
+        let json_headers = || {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            headers
+        };
+        match self {
+            Target::Webhook { headers: extra, secret, .. } => {
+                let body = serde_json::to_string(n).context("Failed to serialize notification")?;
+                let mut headers = extra.clone();
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                if let Some(secret) = secret {
+                    let value = HeaderValue::from_str(&sign(secret, &body)).expect("hex signature is a valid header");
+                    headers.insert(SIGNATURE_HEADER, value);
+                }
+                Ok((headers, body))
+            }
+            Target::Discord { .. } => Ok((json_headers(), discord_body(n).to_string())),
+            Target::Slack { .. } => Ok((json_headers(), slack_body(n).to_string())),
+            Target::Ntfy { token, .. } => {
+                let mut headers = HeaderMap::new();
+                let mut set = |name: &'static str, value: String| -> Result<()> {
+                    // Header values must be plain ASCII.
+                    let value = HeaderValue::from_str(&value.replace(|c: char| !c.is_ascii(), "-"))
+                        .with_context(|| format!("Invalid ntfy {} header", name))?;
+                    headers.insert(name, value);
+                    Ok(())
+                };
+                set("Title", n.headline())?;
+                set("Tags", format!("{},{}", status_emoji_tag(&n.status), n.model.cli_name()))?;
+                set("Priority", if n.status == StockStatus::InStock { "high" } else { "default" }.to_string())?;
+                set("Click", n.link.clone())?;
+                set("Actions", format!("view, Buy, {}", n.link))?;
+                if let Some(token) = token {
+                    set(AUTHORIZATION.as_str(), format!("Bearer {}", token))?;
+                }
+                let body = format!(
+                    "{} at {} ({})\nRule: {}\n{}",
+                    n.price_display,
+                    n.retailer_display(),
+                    n.status,
+                    n.rule,
+                    n.message
+                );
+                Ok((headers, body))
+            }
+        }
+    }
+
+    async fn send(&self, fetcher: &Fetcher, notification: &Notification, quiet: bool) -> Result<()> {
+        let (headers, body) = self.request(notification)?;
+        fetcher
+            .post(self.url(), &headers, &body, quiet)
+            .await
+            .with_context(|| format!("Notification to {} failed", self.url()))?;
+        Ok(())
+    }
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `body` keyed with `secret`.
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Discord rejects embed fields with an empty value, so blanks show as "-"
+/// like in the table.
+fn discord_field_value(value: &str) -> &str {
+    if value.trim().is_empty() { "-" } else { value }
+}
+
+fn discord_body(n: &Notification) -> serde_json::Value {
+    json!({
+        "username": "gpu_pricecheck",
+        "embeds": [{
+            "title": n.headline(),
+            "url": n.link,
+            "description": n.message,
+            "color": status_rgb(&n.status),
+            "fields": [
+                { "name": "Price", "value": discord_field_value(&n.price_display), "inline": true },
+                { "name": "Status", "value": discord_field_value(&n.status.to_string()), "inline": true },
+                { "name": "Retailer", "value": discord_field_value(n.retailer_display()), "inline": true },
+                { "name": "Rule", "value": discord_field_value(&n.rule), "inline": false },
+            ],
+            "timestamp": n.timestamp.to_rfc3339(),
+        }],
+    })
+}
+
+fn slack_body(n: &Notification) -> serde_json::Value {
+    json!({
+        // Shown in notifications and clients without Block Kit.
+        "text": format!("{} - {}", n.headline(), n.price_display),
+        "attachments": [{
+            "color": format!("#{:06x}", status_rgb(&n.status)),
+            "blocks": [
+                {
+                    "type": "section",
+                    "text": { "type": "mrkdwn", "text": format!("*<{}|{}>*\n{}", n.link, n.headline(), n.message) },
+                },
+                {
+                    "type": "section",
+                    "fields": [
+                        { "type": "mrkdwn", "text": format!("*Price*\n{}", n.price_display) },
+                        { "type": "mrkdwn", "text": format!("*Status*\n{}", n.status) },
+                        { "type": "mrkdwn", "text": format!("*Retailer*\n{}", n.retailer_display()) },
+                        { "type": "mrkdwn", "text": format!("*Rule*\n{}", n.rule) },
+                    ],
+                },
+                {
+                    "type": "actions",
+                    "elements": [{
+                        "type": "button",
+                        "text": { "type": "plain_text", "text": "Buy" },
+                        "url": n.link,
+                    }],
+                },
+            ],
+        }],
+    })
+}
+
+fn parse_url(url: &str, what: &str) -> Result<String> {
+    reqwest::Url::parse(url).with_context(|| format!("Invalid {} URL: {}", what, url))?;
+    Ok(url.to_string())
+}
+
+/// Sends fired alerts to the configured webhooks and chat services through
+/// the shared `Fetcher` (same proxies, TLS settings and retry policy as page
+/// fetches), and by email. Cloning is cheap.
+#[derive(Clone)]
+pub struct Notifier {
+    fetcher: Fetcher,
+    targets: Arc<Vec<Target>>,
+    /// Set when email is configured without `--no-email-alerts`.
+    mailer: Option<Mailer>,
+    quiet: bool,
+}
+
+impl Notifier {
+    pub fn from_args(args: &Args, fetcher: &Fetcher) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &args.webhook_header {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid webhook header name: {}", name))?;
+            let value = HeaderValue::from_str(value)
+                .with_context(|| format!("Invalid value for webhook header {}", name))?;
+            headers.append(name, value);
+        }
+        let mut targets = Vec::new();
+        for url in &args.webhook {
+            targets.push(Target::Webhook {
+                url: parse_url(url, "webhook")?,
+                headers: headers.clone(),
+                secret: args.webhook_secret.clone(),
+            });
+        }
+        for url in &args.discord_webhook {
+            targets.push(Target::Discord { url: parse_url(url, "Discord webhook")? });
+        }
+        for url in &args.slack_webhook {
+            targets.push(Target::Slack { url: parse_url(url, "Slack webhook")? });
+        }
+        for url in &args.ntfy {
+            targets.push(Target::Ntfy { url: parse_url(url, "ntfy topic")?, token: args.ntfy_token.clone() });
+        }
+        Ok(Notifier {
+            fetcher: fetcher.clone(),
+            targets: Arc::new(targets),
+            mailer: Mailer::from_args(args)?.filter(|_| !args.no_email_alerts),
+            quiet: !args.verbose,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty() && self.mailer.is_none()
+    }
+
+    /// Sends every listing of every event to every target, and one email per
+    /// event, concurrently. Fails if any delivery failed (after retries); the
+    /// others still go out.
+    pub async fn send(&self, events: &[AlertEvent]) -> Result<()> {
+        let notifications = Notification::from_events(events);
+        let deliveries = self.targets.iter().flat_map(|target| {
+            notifications.iter().map(move |n| target.send(&self.fetcher, n, self.quiet))
+        });
+        let emails = self.mailer.iter().flat_map(|mailer| events.iter().map(move |event| mailer.send_alert(event)));
+        let (sent, emailed) = futures::join!(join_all(deliveries), join_all(emails));
+        let errors: Vec<anyhow::Error> = sent.into_iter().chain(emailed).filter_map(Result::err).collect();
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.into_iter().next().expect("one error")),
+            n => Err(anyhow!(
+                "{} notifications failed; first error: {:#}",
+                n,
+                errors.first().expect("at least one error")
+            )),
+        }
+    }
+}
+
+/// A made-up alert for `test-notify`, so notifier setups can be checked
+/// without waiting for a restock.
+pub fn sample_event() -> AlertEvent {
+    let title = "NVIDIA GeForce RTX 5090 Founders Edition".to_string();
+    let listing = GpuListing {
+        name: format!("Best Buy : {}", title),
+        retailer: Some("Best Buy".to_string()),
+        product: ProductInfo::from_title(&title),
+        title,
+        status: StockStatus::InStock,
+        price: "$1,999.99".to_string(),
+        price_numeric: Some(1999.99),
+        last_available: "Test notification".to_string(),
+        link: "https://www.nowinstock.net/computers/videocards/nvidia/rtx5090/".to_string(),
+    };
+    AlertEvent {
+        rule: "Test notification".to_string(),
+        model: GpuModel::Rtx5090,
+        fired_at: Utc::now(),
+        message: "This is a test of gpu_pricecheck notifications".to_string(),
+        listings: vec![listing],
+        price_drop: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::FetchConfig;
+    use axum::{extract::State, http::StatusCode, Router};
+    use clap::Parser;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// A request received by the stand-in.
+    struct Received {
+        path: String,
+        headers: axum::http::HeaderMap,
+        body: String,
+    }
+
+    #[derive(Clone, Default)]
+    struct StandIn {
+        received: Arc<Mutex<Vec<Received>>>,
+        /// Requests answered with 503 before the stand-in starts accepting.
+        failures: Arc<AtomicUsize>,
+    }
+
+    async fn receive(
+        State(stand_in): State<StandIn>,
+        uri: axum::http::Uri,
+        headers: axum::http::HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        stand_in.received.lock().unwrap().push(Received { path: uri.path().to_string(), headers, body });
+        let failing = stand_in.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if failing.is_ok() { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::NO_CONTENT }
+    }
+
+    /// Serves a local HTTP stand-in that records every request; returns its base URL.
+    async fn serve(stand_in: StandIn) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().fallback(receive).with_state(stand_in);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    async fn send_sample(args: &[&str]) -> Result<()> {
+        let args = Args::try_parse_from(
+            ["gpu_pricecheck", "--retry-base-delay", "1"].iter().chain(args),
+        )
+        .unwrap();
+        let fetcher = Fetcher::new(FetchConfig::from_args(&args))?;
+        Notifier::from_args(&args, &fetcher)?.send(&[sample_event()]).await
+    }
+
+    fn header<'a>(request: &'a Received, name: &str) -> &'a str {
+        request.headers.get(name).unwrap_or_else(|| panic!("missing {} header", name)).to_str().unwrap()
+    }
+
+    #[test]
+    fn sign_matches_known_hmac() {
+        assert_eq!(
+            sign("secret", r#"{"hello":"world"}"#),
+            "sha256=2677ad3e7c090b2fa2c0fb13020d66d5420879b8316eb356a2d60fb9073bc778"
+        );
+    }
+
+    #[tokio::test]
+    async fn webhook_posts_signed_json() {
+        let stand_in = StandIn::default();
+        let url = format!("{}/hook", serve(stand_in.clone()).await);
+        send_sample(&["--webhook", &url, "--webhook-secret", "secret", "--webhook-header", "X-Team: gpus"])
+            .await
+            .unwrap();
+
+        let received = stand_in.received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let request = &received[0];
+        assert_eq!(request.path, "/hook");
+        assert_eq!(header(request, "content-type"), "application/json");
+        assert_eq!(header(request, "x-team"), "gpus");
+        assert_eq!(header(request, SIGNATURE_HEADER), sign("secret", &request.body));
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["rule"], "Test notification");
+        assert_eq!(body["model"], "5090");
+        assert_eq!(body["name"], "NVIDIA GeForce RTX 5090 Founders Edition");
+        assert_eq!(body["retailer"], "Best Buy");
+        assert_eq!(body["status"], "in_stock");
+        assert_eq!(body["price"], 1999.99);
+        assert_eq!(body["link"], "https://www.nowinstock.net/computers/videocards/nvidia/rtx5090/");
+        assert!(body["timestamp"].is_string());
+        assert!(body.get("price_display").is_none());
+    }
+
+    #[tokio::test]
+    async fn webhook_without_secret_is_unsigned() {
+        let stand_in = StandIn::default();
+        let url = serve(stand_in.clone()).await;
+        send_sample(&["--webhook", &url]).await.unwrap();
+        assert!(stand_in.received.lock().unwrap()[0].headers.get(SIGNATURE_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn webhook_retries_server_errors() {
+        let stand_in = StandIn { failures: Arc::new(AtomicUsize::new(2)), ..Default::default() };
+        let url = serve(stand_in.clone()).await;
+        send_sample(&["--webhook", &url, "--max-attempts", "3"]).await.unwrap();
+        assert_eq!(stand_in.received.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn webhook_gives_up_after_max_attempts() {
+        let stand_in = StandIn { failures: Arc::new(AtomicUsize::new(5)), ..Default::default() };
+        let url = serve(stand_in.clone()).await;
+        let error = send_sample(&["--webhook", &url, "--max-attempts", "2"]).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("503"), "{:#}", error);
+        assert_eq!(stand_in.received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn discord_sends_an_embed_in_the_status_color() {
+        let stand_in = StandIn::default();
+        let url = serve(stand_in.clone()).await;
+        send_sample(&["--discord-webhook", &url]).await.unwrap();
+
+        let received = stand_in.received.lock().unwrap();
+        assert_eq!(header(&received[0], "content-type"), "application/json");
+        let body: serde_json::Value = serde_json::from_str(&received[0].body).unwrap();
+        let embed = &body["embeds"][0];
+        assert_eq!(embed["title"], "5090 In Stock: NVIDIA GeForce RTX 5090 Founders Edition");
+        assert_eq!(embed["url"], "https://www.nowinstock.net/computers/videocards/nvidia/rtx5090/");
+        assert_eq!(embed["color"], 0x28a745);
+        assert_eq!(embed["fields"][0]["name"], "Price");
+        assert_eq!(embed["fields"][0]["value"], "$1,999.99");
+        assert_eq!(embed["fields"][2]["value"], "Best Buy");
+    }
+
+    #[test]
+    fn discord_fields_are_never_empty() {
+        let mut event = sample_event();
+        event.listings[0].price = String::new();
+        event.listings[0].retailer = Some(" ".to_string());
+        event.listings[0].status = StockStatus::Unknown(String::new());
+        let body = discord_body(&Notification::from_events(&[event])[0]);
+        let fields = body["embeds"][0]["fields"].as_array().unwrap();
+        let values: Vec<&str> = fields.iter().map(|field| field["value"].as_str().unwrap()).collect();
+        assert_eq!(values, vec!["-", "-", "-", "Test notification"]);
+    }
+
+    #[tokio::test]
+    async fn slack_sends_blocks_with_a_buy_button() {
+        let stand_in = StandIn::default();
+        let url = serve(stand_in.clone()).await;
+        send_sample(&["--slack-webhook", &url]).await.unwrap();
+
+        let received = stand_in.received.lock().unwrap();
+        let body: serde_json::Value = serde_json::from_str(&received[0].body).unwrap();
+        assert_eq!(body["text"], "5090 In Stock: NVIDIA GeForce RTX 5090 Founders Edition - $1,999.99");
+        let attachment = &body["attachments"][0];
+        assert_eq!(attachment["color"], "#28a745");
+        let button = &attachment["blocks"][2]["elements"][0];
+        assert_eq!(button["type"], "button");
+        assert_eq!(button["url"], "https://www.nowinstock.net/computers/videocards/nvidia/rtx5090/");
+    }
+
+    #[tokio::test]
+    async fn ntfy_sets_headers_and_plain_text_body() {
+        let stand_in = StandIn::default();
+        let url = format!("{}/gpu-alerts", serve(stand_in.clone()).await);
+        send_sample(&["--ntfy", &url, "--ntfy-token", "tk_test"]).await.unwrap();
+
+        let received = stand_in.received.lock().unwrap();
+        let request = &received[0];
+        assert_eq!(request.path, "/gpu-alerts");
+        assert_eq!(header(request, "title"), "5090 In Stock: NVIDIA GeForce RTX 5090 Founders Edition");
+        assert_eq!(header(request, "tags"), "green_circle,5090");
+        assert_eq!(header(request, "priority"), "high");
+        assert_eq!(header(request, "click"), "https://www.nowinstock.net/computers/videocards/nvidia/rtx5090/");
+        assert_eq!(header(request, "authorization"), "Bearer tk_test");
+        assert!(request.body.starts_with("$1,999.99 at Best Buy (In Stock)\nRule: Test notification\n"));
+    }
+}
@@ -1,93 +1,846 @@
-use crate::scraper::GpuListing; // Use GpuListing from scraper module
-use crate::cli::SortColumn; // Use SortColumn from cli module (will create next)
+use crate::scraper::{GpuListing, StockStatus}; // Use GpuListing from scraper module
+use crate::cli::{Args, GpuModel, LinkStyle, SortColumn, TableColumn};
+use crate::alerts::AlertEvent;
+use crate::diff::{DiffKind, ListingChange};
+use crate::fetcher::ModelFetchReport;
+use crate::history::{ChangeKind, HistoryEvent};
+use crate::template::UserTemplate;
 use anyhow::{Context, Result};
+use askama::Template;
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::Write;
 use comfy_table::{
-    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment, Color,
-    ColumnConstraint, ContentArrangement, Table, Width,
+    modifiers::UTF8_ROUND_CORNERS,
+    presets::{UTF8_FULL, UTF8_NO_BORDERS},
+    Attribute, Cell, CellAlignment, Color, ColumnConstraint, ContentArrangement, Table, TableComponent, Width,
 };
 
-fn create_status_cell(status: &str) -> Cell {
-    let cell = Cell::new(status).set_alignment(CellAlignment::Center);
-    match status.to_lowercase().as_str() {
-        "in stock" => cell.add_attribute(Attribute::Bold).fg(Color::Green),
-        "preorder" => cell.add_attribute(Attribute::Bold).fg(Color::Yellow),
-        "out of stock" | "not tracking" => cell.fg(Color::Red),
-        "stock available" => cell.fg(Color::DarkGreen), // Handle Ebay status
-        _ => cell,
+fn create_status_cell(status: &StockStatus) -> Cell {
+    color_status_cell(Cell::new(status), status)
+}
+
+/// The color a status is shown in, shared with the chat notification formatters.
+pub fn status_color(status: &StockStatus) -> Option<Color> {
+    match status {
+        StockStatus::InStock => Some(Color::Green),
+        StockStatus::Preorder => Some(Color::Yellow),
+        StockStatus::OutOfStock | StockStatus::NotTracking => Some(Color::Red),
+        StockStatus::MarketplaceAvailable => Some(Color::DarkGreen), // Handle Ebay status
+        StockStatus::NotifyMe | StockStatus::Unknown(_) => None,
+    }
+}
+
+/// Colors `cell` the way a cell showing `status` is colored.
+fn color_status_cell(cell: Cell, status: &StockStatus) -> Cell {
+    let mut cell = cell.set_alignment(CellAlignment::Center);
+    if matches!(status, StockStatus::InStock | StockStatus::Preorder) {
+        cell = cell.add_attribute(Attribute::Bold);
+    }
+    match status_color(status) {
+        Some(color) => cell.fg(color),
+        None => cell,
+    }
+}
+
+/// Columns shown when `--columns` isn't given.
+const DEFAULT_COLUMNS: &[TableColumn] = &[
+    TableColumn::Retailer,
+    TableColumn::Name,
+    TableColumn::Status,
+    TableColumn::Price,
+    TableColumn::LastAvailable,
+    TableColumn::Link,
+];
+
+/// Below this terminal width, `--links auto` shortens links.
+const FULL_LINKS_MIN_WIDTH: u16 = 160;
+/// Shortened links are cut to this many characters.
+const SHORT_LINK_LEN: usize = 32;
+
+/// How `print_table` lays out listings.
+#[derive(Debug, Clone)]
+pub struct TableLayout {
+    pub columns: Vec<TableColumn>,
+    pub links: LinkStyle,
+    pub compact: bool,
+    /// Overrides the detected terminal width.
+    pub width: Option<u16>,
+}
+
+impl TableLayout {
+    pub fn from_args(args: &Args) -> Self {
+        TableLayout { columns: args.columns.clone(), links: args.links, compact: args.compact, width: args.width }
+    }
+
+    /// The columns to show: `--columns`, or the defaults (without Last
+    /// Available in compact mode), minus the link with `--links hide`.
+    fn resolved_columns(&self) -> Vec<TableColumn> {
+        let mut columns = if self.columns.is_empty() {
+            let mut columns = DEFAULT_COLUMNS.to_vec();
+            if self.compact {
+                columns.retain(|c| *c != TableColumn::LastAvailable);
+            }
+            columns
+        } else {
+            self.columns.clone()
+        };
+        if self.links == LinkStyle::Hide {
+            columns.retain(|c| *c != TableColumn::Link);
+        }
+        columns
+    }
+
+    /// Resolves `auto` for a table that will be `width` characters wide (if known).
+    fn link_style(&self, width: Option<u16>) -> LinkStyle {
+        match self.links {
+            LinkStyle::Auto if self.compact || width.is_some_and(|w| w < FULL_LINKS_MIN_WIDTH) => LinkStyle::Short,
+            LinkStyle::Auto => LinkStyle::Full,
+            style => style,
+        }
     }
 }
 
-pub fn print_table(listings: &[GpuListing], sort_by: &SortColumn, descending: bool) {
-    if listings.is_empty() {
+/// "https://www.bestbuy.com/site/..." → "bestbuy.com/site/…", cut to `SHORT_LINK_LEN`.
+fn shorten_link(link: &str) -> String {
+    let link = link.split_once("://").map_or(link, |(_, rest)| rest);
+    let link = link.strip_prefix("www.").unwrap_or(link);
+    if link.chars().count() <= SHORT_LINK_LEN {
+        return link.to_string();
+    }
+    let mut short: String = link.chars().take(SHORT_LINK_LEN - 1).collect();
+    short.push('…');
+    short
+}
+
+/// Prints listings as a table sized to the terminal, with the columns and link
+/// style from `layout`. Rows whose identity is in `changed` (e.g. since the
+/// previous `--watch` iteration) are highlighted.
+pub fn print_table(
+    records: &[ListingRecord],
+    sort_by: &SortColumn,
+    descending: bool,
+    changed: &HashSet<&str>,
+    layout: &TableLayout,
+) {
+    if records.is_empty() {
         println!("No listings found to display (after filtering).");
         return;
     }
 
     let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    if layout.compact {
+        table
+            .load_preset(UTF8_NO_BORDERS)
+            .remove_style(TableComponent::HorizontalLines)
+            .remove_style(TableComponent::MiddleIntersections)
+            .remove_style(TableComponent::LeftBorderIntersections)
+            .remove_style(TableComponent::RightBorderIntersections);
+    } else {
+        table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+    }
+    if let Some(width) = layout.width {
+        table.set_width(width);
+    }
+    let links = layout.link_style(table.width());
+    let columns = layout.resolved_columns();
 
-    // Adjust constraints - give Name and Link more space
-    table.set_constraints(vec![
-        ColumnConstraint::LowerBoundary(Width::Fixed(60)), // Name
-        ColumnConstraint::LowerBoundary(Width::Fixed(15)), // Status
-        ColumnConstraint::LowerBoundary(Width::Fixed(12)), // Price
-        ColumnConstraint::LowerBoundary(Width::Fixed(35)), // Last Available
-        ColumnConstraint::LowerBoundary(Width::Fixed(40)), // Link
-    ]);
+    // Short columns keep their content width; the name takes what's left, and
+    // long links give way to it.
+    table.set_constraints(columns.iter().map(|column| match column {
+        TableColumn::Model | TableColumn::Status | TableColumn::Price => ColumnConstraint::ContentWidth,
+        TableColumn::Retailer | TableColumn::Brand => ColumnConstraint::UpperBoundary(Width::Fixed(16)),
+        TableColumn::Name => ColumnConstraint::LowerBoundary(Width::Fixed(20)),
+        TableColumn::LastAvailable => ColumnConstraint::UpperBoundary(Width::Fixed(24)),
+        TableColumn::Link if links == LinkStyle::Short => ColumnConstraint::ContentWidth,
+        TableColumn::Link => ColumnConstraint::UpperBoundary(Width::Percentage(35)),
+    }));
 
     // Helper closure for header formatting
-    let header_cell = |name: &str, col: SortColumn| {
-        let arrow = if *sort_by == col { if descending { "▼" } else { "▲" } } else { "" };
-        Cell::new(format!("{} {}", name, arrow)).add_attribute(Attribute::Bold)
+    let header_cell = |name: &str, col: Option<SortColumn>| {
+        let arrow = match col {
+            Some(col) if *sort_by == col => if descending { " ▼" } else { " ▲" },
+            _ => "",
+        };
+        Cell::new(format!("{}{}", name, arrow)).add_attribute(Attribute::Bold)
     };
 
-    table.set_header(vec![
-        header_cell("Name", SortColumn::Name),
-        header_cell("Status", SortColumn::Status).set_alignment(CellAlignment::Center),
-        header_cell("Price", SortColumn::Price).set_alignment(CellAlignment::Right),
-        header_cell("Last Available", SortColumn::LastAvailable).set_alignment(CellAlignment::Right),
-        header_cell("Link", SortColumn::Link),
-    ]);
+    table.set_header(columns.iter().map(|column| match column {
+        TableColumn::Model => header_cell("Model", None),
+        TableColumn::Retailer => header_cell("Retailer", Some(SortColumn::Retailer)),
+        TableColumn::Name => header_cell("Name", Some(SortColumn::Name)),
+        TableColumn::Brand => header_cell("Brand", None),
+        TableColumn::Status => header_cell("Status", Some(SortColumn::Status)).set_alignment(CellAlignment::Center),
+        TableColumn::Price => header_cell("Price", Some(SortColumn::Price)).set_alignment(CellAlignment::Right),
+        TableColumn::LastAvailable => {
+            header_cell("Last Available", Some(SortColumn::LastAvailable)).set_alignment(CellAlignment::Right)
+        }
+        TableColumn::Link => header_cell("Link", Some(SortColumn::Link)),
+    }));
 
-    for item in listings {
-        table.add_row(vec![
-            Cell::new(&item.name),
-            create_status_cell(&item.status),
-            Cell::new(&item.price).set_alignment(CellAlignment::Right),
-            Cell::new(&item.last_available).set_alignment(CellAlignment::Right),
-            Cell::new(&item.link), // Display raw link - terminals usually handle this
-        ]);
+    for record in records {
+        let item = record.listing;
+        let highlight = |cell: Cell| {
+            if changed.contains(item.identity()) {
+                cell.add_attribute(Attribute::Bold).fg(Color::Cyan)
+            } else {
+                cell
+            }
+        };
+        table.add_row(columns.iter().map(|column| match column {
+            TableColumn::Model => highlight(Cell::new(record.model.cli_name())),
+            TableColumn::Retailer => highlight(Cell::new(item.retailer_display())),
+            TableColumn::Name => highlight(Cell::new(&item.title)),
+            TableColumn::Brand => {
+                let brand = item.product.as_ref().and_then(|p| p.brand.as_deref()).unwrap_or("-");
+                highlight(Cell::new(brand))
+            }
+            // The status color goes on top of the highlight.
+            TableColumn::Status => color_status_cell(highlight(Cell::new(&item.status)), &item.status),
+            TableColumn::Price => highlight(Cell::new(&item.price).set_alignment(CellAlignment::Right)),
+            TableColumn::LastAvailable => {
+                highlight(Cell::new(&item.last_available).set_alignment(CellAlignment::Right))
+            }
+            // Full links are printed raw so terminals can make them clickable.
+            TableColumn::Link if links == LinkStyle::Short => highlight(Cell::new(shorten_link(&item.link))),
+            TableColumn::Link => highlight(Cell::new(&item.link)),
+        }));
     }
 
     println!("{}", table);
 }
 
-pub fn print_json(listings: &[GpuListing]) -> Result<()> {
-    let json = serde_json::to_string_pretty(listings)
-        .context("Failed to serialize listings to JSON")?;
+/// Prints `items` as a pretty JSON array. `what` names them in errors (e.g. "listings").
+pub fn print_json<T: Serialize>(what: &str, items: &[T]) -> Result<()> {
+    let json = serde_json::to_string_pretty(items)
+        .with_context(|| format!("Failed to serialize {} to JSON", what))?;
     println!("{}", json);
     Ok(())
 }
 
-pub fn print_yaml(listings: &[GpuListing]) -> Result<()> {
-    let yaml = serde_yaml::to_string(listings)
-        .context("Failed to serialize listings to YAML")?;
+/// Like [`print_json`], as YAML.
+pub fn print_yaml<T: Serialize>(what: &str, items: &[T]) -> Result<()> {
+    let yaml = serde_yaml::to_string(items)
+        .with_context(|| format!("Failed to serialize {} to YAML", what))?;
     println!("{}", yaml);
     Ok(())
 }
 
-pub fn print_toml(listings: &[GpuListing]) -> Result<()> {
-    // TOML requires a top-level table. We'll wrap the list in a table named "listings".
-    #[derive(serde::Serialize)]
-    struct TomlWrapper<'a> {
-        listings: &'a [GpuListing],
-    }
-    let wrapper = TomlWrapper { listings };
+/// TOML requires a top-level table, so the list is wrapped in a table under `key`
+/// (e.g. "listings"), which also names the items in error messages.
+pub fn print_toml<T: Serialize>(key: &str, items: &[T]) -> Result<()> {
+    let wrapper = std::collections::BTreeMap::from([(key, items)]);
     let toml = toml::to_string_pretty(&wrapper)
-        .context("Failed to serialize listings to TOML")?;
+        .with_context(|| format!("Failed to serialize {} to TOML", key))?;
     println!("{}", toml);
     Ok(())
-}
\ No newline at end of file
+}
+
+/// A listing together with the model page it came from and when that page was
+/// scraped, for the line-oriented formats where rows end up far from the command
+/// that produced them.
+#[derive(Debug, Serialize)]
+pub struct ListingRecord<'a> {
+    pub model: GpuModel,
+    pub scraped_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub listing: &'a GpuListing,
+}
+
+/// A `--diff` change together with when the page it was found on was scraped,
+/// like [`ListingRecord`] (the change already carries its model).
+#[derive(Debug, Serialize)]
+pub struct ChangeRecord<'a> {
+    pub scraped_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: &'a ListingChange,
+}
+
+/// Prints one compact JSON object per line, ready to append to a log file.
+pub fn print_ndjson<T: Serialize>(items: &[T]) -> Result<()> {
+    write_lines(ndjson_lines(items)?)
+}
+
+fn ndjson_lines<T: Serialize>(items: &[T]) -> Result<Vec<String>> {
+    items
+        .iter()
+        .map(|item| serde_json::to_string(item).context("Failed to serialize listings to JSON"))
+        .collect()
+}
+
+/// Writes lines to stdout, stopping quietly when the reader goes away (e.g. `| head`).
+fn write_lines(lines: impl IntoIterator<Item = String>) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    for line in lines {
+        match writeln!(out, "{}", line) {
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result.context("Failed to write output")?,
+        }
+    }
+    Ok(())
+}
+
+/// Field separator for [`print_delimited`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    fn as_char(self) -> char {
+        match self {
+            Delimiter::Comma => ',',
+            Delimiter::Tab => '\t',
+        }
+    }
+
+    /// CSV fields are quoted when needed (RFC 4180). TSV has no quoting, so
+    /// tabs and line breaks are replaced with spaces.
+    fn escape(self, field: &str) -> String {
+        match self {
+            Delimiter::Comma if field.contains([',', '"', '\n', '\r']) => {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            }
+            Delimiter::Comma => field.to_string(),
+            Delimiter::Tab => field.replace(['\t', '\n', '\r'], " "),
+        }
+    }
+}
+
+/// A record type printed by [`print_delimited`], with its fixed column list.
+/// Columns are the serialized field names (as in JSON), with nested fields as
+/// dotted columns. Every column is always printed, so the header is the same on
+/// every run whichever optional fields happen to be set.
+pub trait DelimitedRecord: Serialize {
+    const COLUMNS: &'static [&'static str];
+}
+
+impl DelimitedRecord for ListingRecord<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "model",
+        "scraped_at",
+        "name",
+        "retailer",
+        "title",
+        "product.brand",
+        "product.line",
+        "product.variants",
+        "status",
+        "price",
+        "price_numeric",
+        "last_available",
+        "link",
+    ];
+}
+
+impl DelimitedRecord for ChangeRecord<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "model",
+        "scraped_at",
+        "kind",
+        "listing.name",
+        "listing.retailer",
+        "listing.title",
+        "listing.product.brand",
+        "listing.product.line",
+        "listing.product.variants",
+        "listing.status",
+        "listing.price",
+        "listing.price_numeric",
+        "listing.last_available",
+        "listing.link",
+        "status_change.from",
+        "status_change.to",
+        "price_change.from",
+        "price_change.to",
+    ];
+}
+
+impl DelimitedRecord for HistoryEvent {
+    const COLUMNS: &'static [&'static str] = &[
+        "time",
+        "model",
+        "retailer",
+        "name",
+        "title",
+        "link",
+        "kind",
+        "from_status",
+        "to_status",
+        "from_price",
+        "to_price",
+    ];
+}
+
+/// Prints items as CSV or TSV: a header row, even when there are no items,
+/// then one row per item. Lists are joined with `;` and fields an item doesn't
+/// have are left empty.
+pub fn print_delimited<T: DelimitedRecord>(items: &[T], delimiter: Delimiter) -> Result<()> {
+    write_lines(delimited_lines(items, delimiter)?)
+}
+
+fn delimited_lines<T: DelimitedRecord>(items: &[T], delimiter: Delimiter) -> Result<Vec<String>> {
+    let separator = delimiter.as_char().to_string();
+    let line = |fields: Vec<&str>| fields.iter().map(|f| delimiter.escape(f)).collect::<Vec<_>>().join(&separator);
+    let mut lines = vec![line(T::COLUMNS.to_vec())];
+    for item in items {
+        let value = serde_json::to_value(item).context("Failed to serialize listings")?;
+        let mut row = Vec::new();
+        flatten_fields(&value, "", &mut row);
+        let fields = T::COLUMNS
+            .iter()
+            .map(|column| row.iter().find(|(key, _)| key == column).map_or("", |(_, v)| v.as_str()))
+            .collect();
+        lines.push(line(fields));
+    }
+    Ok(lines)
+}
+
+/// Flattens a serialized item into `(column, text)` pairs.
+fn flatten_fields(value: &Value, prefix: &str, out: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                let column = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten_fields(field, &column, out);
+            }
+        }
+        _ => out.push((prefix.to_string(), field_text(value))),
+    }
+}
+
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(field_text).collect::<Vec<_>>().join(";"),
+        other => other.to_string(),
+    }
+}
+
+/// Prints each listing through a `--template`.
+pub fn print_template(records: &[ListingRecord], template: &UserTemplate) -> Result<()> {
+    let lines = records.iter().map(|record| template.render(record)).collect::<Result<Vec<_>>>()?;
+    write_lines(lines)
+}
+
+/// Escapes a Markdown table cell: pipes would end the cell and line breaks the row.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// A `[text](url)` link, or just the text when there is no URL.
+fn markdown_link(text: &str, url: &str) -> String {
+    let text = markdown_cell(text).replace('[', "\\[").replace(']', "\\]");
+    if url.is_empty() {
+        return text;
+    }
+    let url = url.replace(' ', "%20").replace('(', "%28").replace(')', "%29").replace('|', "%7C");
+    format!("[{}]({})", text, url)
+}
+
+/// Prints a GitHub-flavored Markdown table. `rows` must already be escaped.
+fn print_markdown_table(header: &[&str], rows: Vec<Vec<String>>, footer: Option<String>) -> Result<()> {
+    let mut lines = vec![
+        format!("| {} |", header.join(" | ")),
+        format!("|{}|", header.iter().map(|_| " --- ").collect::<Vec<_>>().join("|")),
+    ];
+    lines.extend(rows.into_iter().map(|row| format!("| {} |", row.join(" | "))));
+    if let Some(footer) = footer {
+        lines.push(String::new());
+        lines.push(footer);
+    }
+    write_lines(lines)
+}
+
+/// Prints listings as a Markdown table with each name linking to the listing,
+/// followed by when the data was scraped.
+pub fn print_markdown_listings(records: &[ListingRecord]) -> Result<()> {
+    if records.is_empty() {
+        return write_lines(["No listings found (after filtering).".to_string()]);
+    }
+    let rows = records
+        .iter()
+        .map(|record| {
+            let listing = record.listing;
+            let status = markdown_cell(&listing.status.to_string());
+            vec![
+                record.model.cli_name().to_uppercase(),
+                markdown_cell(listing.retailer_display()),
+                markdown_link(&listing.title, &listing.link),
+                if listing.status == StockStatus::InStock { format!("**{}**", status) } else { status },
+                markdown_cell(&listing.price),
+                markdown_cell(&listing.last_available),
+            ]
+        })
+        .collect();
+    let scraped_at = records.iter().map(|record| record.scraped_at).min().map(|t| t.with_timezone(&Local));
+    print_markdown_table(
+        &["Model", "Retailer", "Name", "Status", "Price", "Last Available"],
+        rows,
+        Some(format!("_Data scraped from nowinstock.net at {}_", format_local(scraped_at))),
+    )
+}
+
+/// Prints the changes since the previous scrape as a Markdown table.
+pub fn print_markdown_changes(changes: &[ListingChange]) -> Result<()> {
+    if changes.is_empty() {
+        return write_lines(["No changes since the previous run.".to_string()]);
+    }
+    let rows = changes
+        .iter()
+        .map(|change| {
+            vec![
+                change.kind.label().to_string(),
+                change.model.cli_name().to_uppercase(),
+                markdown_cell(change.listing.retailer_display()),
+                markdown_link(&change.listing.title, &change.listing.link),
+                markdown_cell(&change.status_text()),
+                markdown_cell(&change.price_text()),
+            ]
+        })
+        .collect();
+    print_markdown_table(&["Change", "Model", "Retailer", "Name", "Status", "Price"], rows, None)
+}
+
+/// Prints a history timeline as a Markdown table.
+pub fn print_markdown_history(events: &[HistoryEvent]) -> Result<()> {
+    if events.is_empty() {
+        return write_lines(["No recorded changes found.".to_string()]);
+    }
+    let rows = events
+        .iter()
+        .map(|event| {
+            vec![
+                event.local_time(),
+                markdown_cell(event.retailer.as_deref().unwrap_or("-")),
+                markdown_link(&event.title, &event.link),
+                markdown_cell(&event.describe()),
+            ]
+        })
+        .collect();
+    print_markdown_table(&["Time", "Retailer", "Name", "Change"], rows, None)
+}
+
+fn format_local(time: Option<DateTime<Local>>) -> String {
+    time.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_else(|| "never".to_string())
+}
+
+/// What an HTML report shows.
+pub enum ReportBody<'a> {
+    Listings(&'a [GpuListing]),
+    Changes(&'a [ListingChange]),
+    History(&'a [HistoryEvent]),
+}
+
+#[derive(Template)]
+#[template(path = "report.html")]
+struct ReportTemplate<'a> {
+    title: &'a str,
+    last_updated: String,
+    /// Models that failed to load or needed retries.
+    fetch_notes: Vec<String>,
+    body: ReportBody<'a>,
+    css: &'static str,
+}
+
+/// Prints a self-contained HTML page built from the web UI's templates, with
+/// its stylesheet inlined and no scripts or CDN resources, so it can be saved
+/// to a wiki or attached to an email.
+pub fn print_html_report(
+    title: &str,
+    last_updated: Option<DateTime<Local>>,
+    fetch_reports: &[ModelFetchReport],
+    body: ReportBody,
+) -> Result<()> {
+    let template = ReportTemplate {
+        title,
+        last_updated: format_local(last_updated),
+        fetch_notes: fetch_reports.iter().filter(|r| r.is_notable()).map(ModelFetchReport::summary).collect(),
+        body,
+        css: include_str!("../static/style.css"),
+    };
+    let html = template.render().context("Failed to render HTML report")?;
+    write_lines([html])
+}
+
+/// Prints a history timeline: one row per status or price change.
+pub fn print_history_table(events: &[HistoryEvent]) {
+    if events.is_empty() {
+        println!("No recorded changes found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        Cell::new("Time").add_attribute(Attribute::Bold),
+        Cell::new("Retailer").add_attribute(Attribute::Bold),
+        Cell::new("Name").add_attribute(Attribute::Bold),
+        Cell::new("Change").add_attribute(Attribute::Bold),
+    ]);
+
+    for event in events {
+        let change = Cell::new(event.describe());
+        let change = match event.kind {
+            ChangeKind::FirstSeen => change,
+            ChangeKind::StatusChange => match event.to_status {
+                StockStatus::InStock => change.add_attribute(Attribute::Bold).fg(Color::Green),
+                StockStatus::OutOfStock | StockStatus::NotTracking => change.fg(Color::Red),
+                _ => change.fg(Color::Yellow),
+            },
+            ChangeKind::PriceChange => match (event.from_price, event.to_price) {
+                (Some(from), Some(to)) if to < from => change.fg(Color::Green),
+                (Some(from), Some(to)) if to > from => change.fg(Color::Red),
+                _ => change,
+            },
+        };
+        table.add_row(vec![
+            Cell::new(event.local_time()),
+            Cell::new(event.retailer.as_deref().unwrap_or("-")),
+            Cell::new(&event.title),
+            change,
+        ]);
+    }
+
+    println!("{}", table);
+}
+
+/// Prints the changes since the previous scrape: a marker per kind of change,
+/// status transitions colored by the new status and price moves with an arrow
+/// (green when cheaper).
+pub fn print_diff_table(changes: &[ListingChange]) {
+    if changes.is_empty() {
+        println!("No changes since the previous run.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        Cell::new("Change").add_attribute(Attribute::Bold),
+        Cell::new("Retailer").add_attribute(Attribute::Bold),
+        Cell::new("Name").add_attribute(Attribute::Bold),
+        Cell::new("Status").add_attribute(Attribute::Bold).set_alignment(CellAlignment::Center),
+        Cell::new("Price").add_attribute(Attribute::Bold).set_alignment(CellAlignment::Right),
+        Cell::new("Link").add_attribute(Attribute::Bold),
+    ]);
+
+    for change in changes {
+        let item = &change.listing;
+        let marker = Cell::new(change.kind.label());
+        let marker = match change.kind {
+            DiffKind::Added => marker.add_attribute(Attribute::Bold).fg(Color::Green),
+            DiffKind::Removed => marker.fg(Color::Red),
+            DiffKind::Changed => marker.fg(Color::Yellow),
+        };
+        let status = match &change.status_change {
+            Some(t) => color_status_cell(Cell::new(change.status_text()), &t.to),
+            None if change.kind == DiffKind::Removed => Cell::new(&item.status).set_alignment(CellAlignment::Center),
+            None => create_status_cell(&item.status),
+        };
+        let price_cell = Cell::new(change.price_text());
+        let price_cell = match &change.price_change {
+            Some(_) if change.price_dropped() => price_cell.fg(Color::Green),
+            Some(_) => price_cell.fg(Color::Red),
+            None => price_cell,
+        };
+        table.add_row(vec![
+            marker,
+            Cell::new(item.retailer_display()),
+            Cell::new(&item.title),
+            status,
+            price_cell.set_alignment(CellAlignment::Right),
+            Cell::new(&item.link),
+        ]);
+    }
+
+    println!("{}", table);
+}
+
+/// Lists models that failed to load or needed retries. Written to stderr so
+/// machine-readable formats on stdout stay parseable.
+pub fn print_fetch_report(reports: &[ModelFetchReport]) {
+    let notable: Vec<&ModelFetchReport> = reports.iter().filter(|r| r.is_notable()).collect();
+    if notable.is_empty() {
+        return;
+    }
+    eprintln!("Fetch issues:");
+    for report in notable {
+        eprintln!("  {}", report.summary());
+    }
+}
+
+/// Prints fired alert rules and their listings to stderr, alongside the
+/// fetch report.
+pub fn print_alerts(events: &[AlertEvent]) {
+    for event in events {
+        eprintln!("Alert \"{}\": {}", event.rule, event.message);
+        for listing in &event.listings {
+            eprintln!(
+                "  {} | {} | {} | {} | {}",
+                listing.retailer_display(),
+                listing.title,
+                listing.status,
+                listing.price,
+                listing.link
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Transition;
+    use crate::scraper::{ProductInfo, ProductVariant};
+
+    /// A listing whose name and title need CSV quoting.
+    fn listing(retailer: Option<&str>, product: Option<ProductInfo>, price_numeric: Option<f64>) -> GpuListing {
+        GpuListing {
+            name: "Best Buy - GeForce RTX 5090, 32GB".to_string(),
+            retailer: retailer.map(str::to_string),
+            title: "GeForce RTX 5090, 32GB".to_string(),
+            product,
+            last_available: "1 min ago".to_string(),
+            ..GpuListing::for_test("5090", StockStatus::InStock, price_numeric)
+        }
+    }
+
+    fn record(listing: &GpuListing) -> ListingRecord<'_> {
+        ListingRecord { model: GpuModel::Rtx5090, scraped_at: DateTime::UNIX_EPOCH, listing }
+    }
+
+    #[test]
+    fn ndjson_lines_carry_model_and_scrape_time() {
+        let listing = listing(Some("Best Buy"), None, Some(1999.99));
+        let change = ListingChange {
+            model: GpuModel::Rtx5090,
+            kind: DiffKind::Changed,
+            listing: listing.clone(),
+            status_change: Some(Transition { from: StockStatus::OutOfStock, to: StockStatus::InStock }),
+            price_change: None,
+        };
+        let changes = [ChangeRecord { scraped_at: DateTime::UNIX_EPOCH, change: &change }];
+        let lines = ndjson_lines(&changes).unwrap().into_iter().chain(ndjson_lines(&[record(&listing)]).unwrap());
+        for line in lines {
+            let value: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(value["model"], "5090", "{}", line);
+            assert_eq!(value["scraped_at"], "1970-01-01T00:00:00Z", "{}", line);
+        }
+    }
+
+    fn layout(columns: &[TableColumn], links: LinkStyle, compact: bool) -> TableLayout {
+        TableLayout { columns: columns.to_vec(), links, compact, width: None }
+    }
+
+    #[test]
+    fn columns_follow_the_selection_minus_hidden_links() {
+        let selected = [TableColumn::Link, TableColumn::Name, TableColumn::Price];
+        assert_eq!(layout(&selected, LinkStyle::Auto, false).resolved_columns(), selected);
+        assert_eq!(
+            layout(&selected, LinkStyle::Hide, false).resolved_columns(),
+            vec![TableColumn::Name, TableColumn::Price]
+        );
+        assert_eq!(layout(&[], LinkStyle::Full, false).resolved_columns(), DEFAULT_COLUMNS);
+    }
+
+    #[test]
+    fn compact_drops_last_available_from_the_defaults() {
+        let columns = layout(&[], LinkStyle::Auto, true).resolved_columns();
+        assert_eq!(
+            columns,
+            vec![TableColumn::Retailer, TableColumn::Name, TableColumn::Status, TableColumn::Price, TableColumn::Link]
+        );
+        // An explicit selection is kept as given.
+        let selected = [TableColumn::Name, TableColumn::LastAvailable];
+        assert_eq!(layout(&selected, LinkStyle::Auto, true).resolved_columns(), selected);
+    }
+
+    #[test]
+    fn auto_links_are_short_on_narrow_or_compact_tables() {
+        let auto = layout(&[], LinkStyle::Auto, false);
+        assert_eq!(auto.link_style(Some(FULL_LINKS_MIN_WIDTH - 1)), LinkStyle::Short);
+        assert_eq!(auto.link_style(Some(FULL_LINKS_MIN_WIDTH)), LinkStyle::Full);
+        assert_eq!(auto.link_style(None), LinkStyle::Full);
+        assert_eq!(layout(&[], LinkStyle::Auto, true).link_style(Some(200)), LinkStyle::Short);
+        // Explicit styles aren't changed by the width.
+        assert_eq!(layout(&[], LinkStyle::Full, false).link_style(Some(80)), LinkStyle::Full);
+        assert_eq!(layout(&[], LinkStyle::Short, false).link_style(Some(200)), LinkStyle::Short);
+    }
+
+    #[test]
+    fn shorten_link_drops_scheme_and_www() {
+        assert_eq!(shorten_link("https://www.bestbuy.com/site/5090"), "bestbuy.com/site/5090");
+        assert_eq!(shorten_link("http://newegg.com/p/1"), "newegg.com/p/1");
+        assert_eq!(shorten_link("bhphotovideo.com/c/1"), "bhphotovideo.com/c/1");
+    }
+
+    #[test]
+    fn shorten_link_truncates_past_the_limit() {
+        let exact = format!("https://www.{}", "a".repeat(SHORT_LINK_LEN));
+        assert_eq!(shorten_link(&exact), "a".repeat(SHORT_LINK_LEN));
+        let long = format!("https://www.{}", "a".repeat(SHORT_LINK_LEN + 1));
+        let short = shorten_link(&long);
+        assert_eq!(short.chars().count(), SHORT_LINK_LEN);
+        assert_eq!(short, format!("{}…", "a".repeat(SHORT_LINK_LEN - 1)));
+    }
+
+    #[test]
+    fn csv_escape_quotes_only_when_needed() {
+        assert_eq!(Delimiter::Comma.escape("plain"), "plain");
+        assert_eq!(Delimiter::Comma.escape("RTX 5090, 32GB"), "\"RTX 5090, 32GB\"");
+        assert_eq!(Delimiter::Comma.escape("12\" card"), "\"12\"\" card\"");
+        assert_eq!(Delimiter::Comma.escape("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn tsv_escape_replaces_tabs_and_line_breaks() {
+        assert_eq!(Delimiter::Tab.escape("a\tb\r\nc, \"d\""), "a b  c, \"d\"");
+    }
+
+    #[test]
+    fn header_is_printed_without_rows() {
+        let lines = delimited_lines::<ListingRecord>(&[], Delimiter::Comma).unwrap();
+        assert_eq!(lines, vec![ListingRecord::COLUMNS.join(",")]);
+        let lines = delimited_lines::<HistoryEvent>(&[], Delimiter::Tab).unwrap();
+        assert_eq!(lines, vec![HistoryEvent::COLUMNS.join("\t")]);
+    }
+
+    #[test]
+    fn header_does_not_depend_on_optional_fields() {
+        let bare = listing(None, None, None);
+        let full = listing(
+            Some("Best Buy"),
+            Some(ProductInfo {
+                brand: Some("NVIDIA".to_string()),
+                line: None,
+                variants: vec![ProductVariant::Oc, ProductVariant::White],
+            }),
+            Some(1999.99),
+        );
+        let bare_lines = delimited_lines(&[record(&bare)], Delimiter::Comma).unwrap();
+        let full_lines = delimited_lines(&[record(&full)], Delimiter::Comma).unwrap();
+        assert_eq!(bare_lines[0], full_lines[0]);
+        assert_eq!(
+            bare_lines[1],
+            "5090,1970-01-01T00:00:00Z,\"Best Buy - GeForce RTX 5090, 32GB\",,\"GeForce RTX 5090, 32GB\",,,,\
+             in_stock,,,1 min ago,https://example.com/5090"
+        );
+        assert_eq!(
+            full_lines[1],
+            "5090,1970-01-01T00:00:00Z,\"Best Buy - GeForce RTX 5090, 32GB\",Best Buy,\"GeForce RTX 5090, 32GB\",\
+             NVIDIA,,oc;white,in_stock,$1999.99,1999.99,1 min ago,https://example.com/5090"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::alerts::AlertEngine;
+use crate::cache::PageCache;
+use crate::cli::GpuModel;
+use crate::fetcher::{Fetcher, ModelFetchReport};
+use crate::output;
+use crate::scraper::{self, GpuListing};
+use crate::recorder::ScrapeRecorder;
+use crate::web::HtmlSource;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How often each model is refreshed in the background.
+#[derive(Debug, Clone)]
+pub struct PollerConfig {
+    pub default_interval: Duration,
+    /// Per-model overrides of `default_interval`.
+    pub model_intervals: HashMap<GpuModel, Duration>,
+}
+
+impl PollerConfig {
+    pub fn interval_for(&self, model: GpuModel) -> Duration {
+        self.model_intervals.get(&model).copied().unwrap_or(self.default_interval)
+    }
+}
+
+/// Latest known state of one model, as maintained by the poller.
+#[derive(Debug, Clone, Default)]
+pub struct ModelSnapshot {
+    /// Listings from the last successful refresh.
+    pub listings: Vec<GpuListing>,
+    /// When the page behind `listings` was fetched.
+    pub fetched_at: Option<DateTime<Local>>,
+    /// When the last refresh (successful or not) finished.
+    pub last_attempt: Option<DateTime<Local>>,
+    /// Report from the last refresh.
+    pub last_report: Option<ModelFetchReport>,
+    /// Refreshes that have failed in a row since the last success.
+    pub consecutive_failures: u32,
+}
+
+impl ModelSnapshot {
+    /// A note for the page when this model's data is missing, stale or flaky.
+    pub fn status_note(&self, model: GpuModel) -> Option<String> {
+        let name = model.cli_name();
+        if self.consecutive_failures > 0 {
+            let error = self
+                .last_report
+                .as_ref()
+                .and_then(|r| r.error.clone())
+                .unwrap_or_else(|| "unknown error".to_string());
+            let data = match self.fetched_at {
+                Some(t) => format!(
+                    "showing data from {} ({} min old)",
+                    t.format("%Y-%m-%d %H:%M:%S"),
+                    (Local::now() - t).num_minutes()
+                ),
+                None => "no data available yet".to_string(),
+            };
+            return Some(format!(
+                "{}: last {} refresh(es) failed ({}); {}",
+                name, self.consecutive_failures, error, data
+            ));
+        }
+        self.last_report
+            .as_ref()
+            .filter(|r| r.is_notable())
+            .map(ModelFetchReport::summary)
+    }
+}
+
+/// Shared store of the latest snapshot per model, read by the web handlers.
+#[derive(Debug, Default)]
+pub struct SnapshotStore {
+    models: RwLock<HashMap<GpuModel, ModelSnapshot>>,
+}
+
+impl SnapshotStore {
+    pub fn get(&self, model: GpuModel) -> ModelSnapshot {
+        self.models
+            .read()
+            .expect("snapshot lock poisoned")
+            .get(&model)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn update(&self, model: GpuModel, apply: impl FnOnce(&mut ModelSnapshot)) {
+        let mut models = self.models.write().expect("snapshot lock poisoned");
+        apply(models.entry(model).or_default());
+    }
+}
+
+/// Starts one background task per model that refreshes its listings on the
+/// model's interval and stores the result in `store`. Pages are loaded
+/// through `cache`, shared with on-demand page views.
+pub fn spawn(
+    fetcher: Fetcher,
+    source: HtmlSource,
+    cache: Arc<PageCache>,
+    store: Arc<SnapshotStore>,
+    recorder: ScrapeRecorder,
+    alerts: AlertEngine,
+    config: PollerConfig,
+) {
+    for model in GpuModel::value_variants().iter().copied() {
+        let fetcher = fetcher.clone();
+        let source = source.clone();
+        let cache = cache.clone();
+        let store = store.clone();
+        let recorder = recorder.clone();
+        let alerts = alerts.clone();
+        let period = config.interval_for(model);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            // Don't fire a burst of refreshes to catch up after a slow fetch.
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                refresh_model(&fetcher, &source, &cache, &store, &recorder, &alerts, model).await;
+            }
+        });
+    }
+}
+
+async fn refresh_model(
+    fetcher: &Fetcher,
+    source: &HtmlSource,
+    cache: &PageCache,
+    store: &SnapshotStore,
+    recorder: &ScrapeRecorder,
+    alerts: &AlertEngine,
+    model: GpuModel,
+) {
+    let url = scraper::model_url(model);
+    let load = cache.get_or_fetch(&url, || source.load(fetcher, model, true));
+    let result = match load.await {
+        Ok((page, fresh)) => scraper::parse_listings(&page.body, true).map(|listings| (listings, page, fresh)),
+        Err(e) => Err(e),
+    };
+    let now = Local::now();
+    // A page a viewer fetched within the cache TTL was already recorded then.
+    if let Ok((listings, page, true)) = &result {
+        if let Err(e) = recorder.record(model, listings, page.fetched_at).await {
+            eprintln!("Failed to record observations for {:?}: {:#}", model, e);
+        }
+        output::print_alerts(&alerts.check(model, listings, page.fetched_at));
+    }
+    match result {
+        Ok((listings, page, _)) => store.update(model, |snapshot| {
+            snapshot.listings = listings;
+            snapshot.fetched_at = Some(page.fetched_at);
+            snapshot.last_attempt = Some(now);
+            snapshot.last_report = Some(ModelFetchReport::success(model, page.attempts));
+            snapshot.consecutive_failures = 0;
+        }),
+        Err(e) => {
+            eprintln!("Background refresh failed for {:?}: {:#}", model, e);
+            store.update(model, |snapshot| {
+                snapshot.last_attempt = Some(now);
+                snapshot.last_report = Some(ModelFetchReport::failure(model, &e));
+                snapshot.consecutive_failures += 1;
+            });
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::cli::{Args, GpuModel};
+use crate::scraper::GpuListing;
+use crate::storage::Storage;
+use crate::web::HtmlSource;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::sync::Arc;
+
+/// Records every freshly scraped page of listings to the history database,
+/// unless recording is turned off for this run.
+#[derive(Clone, Default)]
+pub struct ScrapeRecorder {
+    storage: Option<Arc<Storage>>,
+}
+
+impl ScrapeRecorder {
+    /// Runs against saved pages (`--from-file`/`--from-dir`/`--replay`)
+    /// aren't real observations, so they're never recorded.
+    pub fn from_args(args: &Args, source: &HtmlSource, storage: &Arc<Storage>) -> Self {
+        let storage = (!args.no_history && !source.is_saved_page()).then(|| storage.clone());
+        ScrapeRecorder { storage }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.storage.is_some()
+    }
+
+    /// Records one scrape of `model`, fetched at `fetched_at`. Returns the
+    /// number of new rows (0 when recording is off). The write runs on the
+    /// blocking thread pool, so it doesn't hold up other tasks.
+    pub async fn record(&self, model: GpuModel, listings: &[GpuListing], fetched_at: DateTime<Local>) -> Result<usize> {
+        let Some(storage) = self.storage.clone() else {
+            return Ok(0);
+        };
+        let listings = listings.to_vec();
+        tokio::task::spawn_blocking(move || storage.record(model, &listings, fetched_at.into()))
+            .await
+            .context("History database writer panicked")?
+    }
+}
@@ -2,7 +2,9 @@ use anyhow::{Result, anyhow}; // Make sure anyhow is imported
 use regex::Regex;
 use scraper::{Html, Selector, ElementRef}; // Import ElementRef
 use lazy_static::lazy_static;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use clap::ValueEnum;
+use std::fmt;
 use crate::cli::GpuModel; // Add this import to use GpuModel
 
 /// Returns the base URL for fetching listings based on the GPU model.
@@ -14,16 +16,297 @@ pub fn get_base_url(gpu: GpuModel) -> &'static str {
     }
 }
 
+/// Full nowinstock URL of the listings page for a GPU model.
+pub fn model_url(gpu: GpuModel) -> String {
+    format!("{}{}", get_base_url(gpu), gpu)
+}
+
 pub const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36"; // Made public
 
 lazy_static! {
     static ref PRICE_RE: Regex = Regex::new(r"[\d,]+\.\d{2}").unwrap();
 }
 
+/// Retailers that nowinstock prefixes onto listing names, as
+/// (canonical name, lowercase spellings seen on the site).
+const RETAILERS: &[(&str, &[&str])] = &[
+    ("Amazon", &["amazon"]),
+    ("Best Buy", &["best buy", "bestbuy"]),
+    ("Newegg", &["newegg"]),
+    ("B&H", &["b&h", "b&h photo", "bhphotovideo"]),
+    ("Ebay", &["ebay"]),
+    ("Walmart", &["walmart"]),
+    ("Micro Center", &["micro center", "microcenter"]),
+    ("Adorama", &["adorama"]),
+    ("Antonline", &["antonline"]),
+    ("Nvidia", &["nvidia store", "nvidia"]),
+];
+
+/// Normalizes a retailer or brand name for comparison, so "bestbuy",
+/// "Best Buy" and "BEST-BUY" all match.
+pub fn name_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Splits the retailer prefix off a listing name, e.g.
+/// "Best Buy : RTX 5090 Founders Edition" -> (Some("Best Buy"), "RTX 5090 Founders Edition").
+/// Returns the trimmed name unchanged when no known retailer is found.
+fn split_retailer(name: &str) -> (Option<String>, String) {
+    let trimmed = name.trim();
+    for (canonical, spellings) in RETAILERS {
+        for spelling in *spellings {
+            let matches_prefix = trimmed
+                .get(..spelling.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(spelling));
+            if !matches_prefix {
+                continue;
+            }
+            let rest = &trimmed[spelling.len()..];
+            // Require a separator so e.g. "Nvidia GeForce..." product names aren't split.
+            let rest_trimmed = rest.trim_start();
+            if !rest.is_empty() && !rest_trimmed.starts_with([':', '-', '|', '–']) {
+                continue;
+            }
+            let title = rest_trimmed.trim_start_matches([':', '-', '|', '–']).trim();
+            let title = if title.is_empty() { trimmed } else { title };
+            return (Some(canonical.to_string()), title.to_string());
+        }
+    }
+    (None, trimmed.to_string())
+}
+
+/// Stock status of a listing, normalized from the text shown on nowinstock.
+///
+/// Known statuses serialize as stable snake_case keys (e.g. `in_stock`) so
+/// machine-readable output doesn't change if the site rewords its labels.
+/// Anything we don't recognize is kept verbatim in `Unknown`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StockStatus {
+    InStock,
+    Preorder,
+    NotifyMe,
+    /// Marketplace listings (eBay) reporting available stock.
+    MarketplaceAvailable,
+    OutOfStock,
+    NotTracking,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl StockStatus {
+    /// Maps the status text shown on the site to a `StockStatus`.
+    pub fn from_site_text(text: &str) -> Self {
+        let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        match normalized.as_str() {
+            "in stock" => StockStatus::InStock,
+            "preorder" | "pre-order" | "pre order" => StockStatus::Preorder,
+            "notify me" => StockStatus::NotifyMe,
+            "stock available" => StockStatus::MarketplaceAvailable,
+            "out of stock" => StockStatus::OutOfStock,
+            "not tracking" => StockStatus::NotTracking,
+            _ => StockStatus::Unknown(text.trim().to_string()),
+        }
+    }
+
+    /// Stable machine-readable key, matching the serialized form
+    /// (e.g. "in_stock"); unknown statuses return their original text.
+    pub fn key(&self) -> &str {
+        match self {
+            StockStatus::InStock => "in_stock",
+            StockStatus::Preorder => "preorder",
+            StockStatus::NotifyMe => "notify_me",
+            StockStatus::MarketplaceAvailable => "marketplace_available",
+            StockStatus::OutOfStock => "out_of_stock",
+            StockStatus::NotTracking => "not_tracking",
+            StockStatus::Unknown(text) => text,
+        }
+    }
+
+    /// Inverse of [`StockStatus::key`].
+    pub fn from_key(key: &str) -> Self {
+        match key {
+            "in_stock" => StockStatus::InStock,
+            "preorder" => StockStatus::Preorder,
+            "notify_me" => StockStatus::NotifyMe,
+            "marketplace_available" => StockStatus::MarketplaceAvailable,
+            "out_of_stock" => StockStatus::OutOfStock,
+            "not_tracking" => StockStatus::NotTracking,
+            _ => StockStatus::Unknown(key.to_string()),
+        }
+    }
+
+    /// Whether the listing can currently be bought or ordered in some form.
+    /// Out of Stock and Not Tracking listings are hidden unless `--all` is used.
+    pub fn is_available(&self) -> bool {
+        !matches!(self, StockStatus::OutOfStock | StockStatus::NotTracking)
+    }
+
+    /// CSS class suffix used by the web UI (`status-<class>`).
+    pub fn css_class(&self) -> String {
+        match self {
+            StockStatus::InStock => "in-stock".to_string(),
+            StockStatus::Preorder => "preorder".to_string(),
+            StockStatus::NotifyMe => "notify-me".to_string(),
+            StockStatus::MarketplaceAvailable => "ebay".to_string(),
+            StockStatus::OutOfStock => "out-of-stock".to_string(),
+            StockStatus::NotTracking => "not-tracking".to_string(),
+            StockStatus::Unknown(text) => text
+                .to_lowercase()
+                .replace(' ', "-")
+                .chars()
+                .filter(|c| c.is_alphanumeric() || *c == '-')
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for StockStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StockStatus::InStock => write!(f, "In Stock"),
+            StockStatus::Preorder => write!(f, "Preorder"),
+            StockStatus::NotifyMe => write!(f, "Notify Me"),
+            StockStatus::MarketplaceAvailable => write!(f, "Stock Available"),
+            StockStatus::OutOfStock => write!(f, "Out of Stock"),
+            StockStatus::NotTracking => write!(f, "Not Tracking"),
+            StockStatus::Unknown(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Board partners and their product lines, as (canonical brand, brand
+/// spellings, product lines). Lines are checked in order, so more specific
+/// names must come before shorter ones they contain (e.g. "Gaming X Trio"
+/// before "Gaming Trio").
+const BRANDS: &[(&str, &[&str], &[&str])] = &[
+    ("ASUS", &["asus"], &["ROG Astral", "ROG Strix", "TUF Gaming", "TUF", "Prime", "Dual"]),
+    ("MSI", &["msi"], &[
+        "Suprim Liquid", "Suprim", "Gaming X Trio", "Gaming Trio", "Vanguard",
+        "Ventus 3X", "Ventus 2X", "Ventus", "Inspire", "Shadow",
+    ]),
+    ("Gigabyte", &["gigabyte"], &[
+        "Aorus Xtreme", "Aorus Master", "Aorus Elite", "Aorus", "Gaming", "Windforce", "Eagle", "Aero",
+    ]),
+    ("Zotac", &["zotac"], &["AMP Extreme Infinity", "AMP Extreme", "AMP", "Solid", "Twin Edge", "Trinity"]),
+    ("PNY", &["pny"], &["XLR8", "Epic-X", "Verto"]),
+    ("Sapphire", &["sapphire"], &["Nitro+", "Pulse", "Pure"]),
+    ("PowerColor", &["powercolor", "power color"], &["Red Devil", "Hellhound", "Reaper", "Red Dragon"]),
+    ("XFX", &["xfx"], &["Mercury", "Quicksilver", "Swift"]),
+    ("ASRock", &["asrock"], &["Taichi", "Steel Legend", "Challenger"]),
+    ("Gainward", &["gainward"], &["Phantom", "Phoenix"]),
+    ("Palit", &["palit"], &["GameRock", "GamingPro", "Infinity"]),
+    ("Inno3D", &["inno3d"], &["iChill", "X3", "Twin X2"]),
+    ("Colorful", &["colorful"], &["iGame", "Vulcan", "Advanced"]),
+];
+
+/// Spellings of each variant flag as they appear in listing names.
+const VARIANTS: &[(ProductVariant, &[&str])] = &[
+    (ProductVariant::Oc, &["oc", "overclocked"]),
+    (ProductVariant::White, &["white", "wht"]),
+    (ProductVariant::FoundersEdition, &["founders edition", "founders"]),
+];
+
+/// Lowercases a listing name and replaces punctuation with spaces, padding
+/// both ends so whole words can be matched with `contains(" word ")`.
+fn word_haystack(text: &str) -> String {
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '+'))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    format!(" {} ", words.join(" "))
+}
+
+fn contains_words(haystack: &str, needle: &str) -> bool {
+    haystack.contains(&word_haystack(needle))
+}
+
+/// Variant flags that can be detected in a listing name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductVariant {
+    Oc,
+    White,
+    #[value(name = "fe", alias = "founders")]
+    #[serde(alias = "fe")]
+    FoundersEdition,
+}
+
+impl fmt::Display for ProductVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProductVariant::Oc => write!(f, "OC"),
+            ProductVariant::White => write!(f, "White"),
+            ProductVariant::FoundersEdition => write!(f, "Founders Edition"),
+        }
+    }
+}
+
+/// Structured attributes parsed from a listing's product title.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brand: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<ProductVariant>,
+}
+
+impl ProductInfo {
+    /// Extracts brand, product line and variant flags from a product title
+    /// using the `BRANDS` and `VARIANTS` dictionaries. Returns `None` when
+    /// nothing is recognized.
+    pub fn from_title(title: &str) -> Option<Self> {
+        let haystack = word_haystack(title);
+        let mut brand = None;
+        let mut line = None;
+        for (canonical, spellings, lines) in BRANDS {
+            if spellings.iter().any(|s| contains_words(&haystack, s)) {
+                brand = Some(canonical.to_string());
+                line = lines
+                    .iter()
+                    .find(|l| contains_words(&haystack, l))
+                    .map(|l| l.to_string());
+                break;
+            }
+        }
+        let variants: Vec<ProductVariant> = VARIANTS
+            .iter()
+            .filter(|(_, spellings)| spellings.iter().any(|s| contains_words(&haystack, s)))
+            .map(|(variant, _)| *variant)
+            .collect();
+        // Founders Edition cards are sold by NVIDIA itself; "NVIDIA" otherwise
+        // just names the chip, so it isn't treated as a brand.
+        if brand.is_none() && variants.contains(&ProductVariant::FoundersEdition) {
+            brand = Some("NVIDIA".to_string());
+        }
+        if brand.is_none() && variants.is_empty() {
+            return None;
+        }
+        Some(ProductInfo { brand, line, variants })
+    }
+
+    pub fn has_variant(&self, variant: ProductVariant) -> bool {
+        self.variants.contains(&variant)
+    }
+}
+
 #[derive(Debug, Clone, Serialize)] // Add Serialize derive
 pub struct GpuListing {
+    /// Full listing name as shown on the site, including the retailer prefix.
     pub name: String,
-    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retailer: Option<String>,
+    /// Product title with the retailer prefix removed.
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product: Option<ProductInfo>,
+    pub status: StockStatus,
     pub price: String,
     #[serde(skip_serializing_if = "Option::is_none")] // Don't include in JSON/YAML if None
     pub price_numeric: Option<f64>,
@@ -33,12 +316,39 @@ pub struct GpuListing {
 
 impl GpuListing {
     pub fn status_class(&self) -> String {
-        self.status
-            .to_lowercase()
-            .replace(' ', "-")
-            .chars()
-            .filter(|c| c.is_alphanumeric() || *c == '-')
-            .collect()
+        self.status.css_class()
+    }
+
+    /// Stable identity of a listing across scrapes: its link, or the name
+    /// when the row has no link.
+    pub fn identity(&self) -> &str {
+        if self.link.is_empty() { &self.name } else { &self.link }
+    }
+
+    /// Retailer name for display, or "-" when it couldn't be determined.
+    pub fn retailer_display(&self) -> &str {
+        self.retailer.as_deref().unwrap_or("-")
+    }
+}
+
+#[cfg(test)]
+impl GpuListing {
+    /// A Best Buy RTX 5090 listing for tests, told apart by `slug`: it's in
+    /// the title and the link (`https://example.com/<slug>`). Override other
+    /// fields with struct update syntax.
+    pub(crate) fn for_test(slug: &str, status: StockStatus, price: Option<f64>) -> Self {
+        let title = format!("RTX 5090 {}", slug);
+        GpuListing {
+            name: format!("Best Buy : {}", title),
+            retailer: Some("Best Buy".to_string()),
+            title,
+            product: None,
+            status,
+            price: price.map_or_else(String::new, |p| format!("${:.2}", p)),
+            price_numeric: price,
+            last_available: String::new(),
+            link: format!("https://example.com/{}", slug),
+        }
     }
 }
 
@@ -54,7 +364,7 @@ fn parse_standard_row(cells: &[ElementRef], link_selector: &Selector) -> Option<
     if cells.len() < 4 {
         return None; // Skip incomplete rows
     }
-    let (name, link) = cells.get(0)
+    let (name, link) = cells.first()
         .and_then(|cell| cell.select(link_selector).next())
         .map(|link_el| {
             let name_text = link_el.text().collect::<String>().trim().to_string();
@@ -70,10 +380,9 @@ fn parse_standard_row(cells: &[ElementRef], link_selector: &Selector) -> Option<
             cell.select(link_selector).next()
                .map(|link_el| link_el.text().collect::<String>())
                .unwrap_or_else(|| cell.text().collect::<String>())
-               .trim()
-               .to_string()
         })
-        .unwrap_or_else(|| "N/A".to_string());
+        .map(|text| StockStatus::from_site_text(&text))
+        .unwrap_or_else(|| StockStatus::Unknown("N/A".to_string()));
     let price = cells.get(2)
         .map(|cell| cell.text().collect::<String>().trim().to_string())
         .unwrap_or_else(|| "-".to_string());
@@ -84,8 +393,13 @@ fn parse_standard_row(cells: &[ElementRef], link_selector: &Selector) -> Option<
             cell.value().attr("title").map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).unwrap_or(main_text)
         })
         .unwrap_or_else(|| "-".to_string());
+    let (retailer, title) = split_retailer(&name);
+    let product = ProductInfo::from_title(&title);
     Some(GpuListing {
         name,
+        retailer,
+        title,
+        product,
         status,
         price,
         price_numeric,
@@ -103,9 +417,17 @@ fn parse_ebay_row(cells: &[ElementRef], link_selector: &Selector) -> Option<GpuL
         let name_text = link_element.text().collect::<String>().trim().to_string();
         if name_text.contains("Ebay") {
             let link = link_element.value().attr("href").unwrap_or("").to_string();
-            let status = cells[1].text().collect::<String>().trim().to_string();
+            // Marketplace rows report generic availability rather than retailer stock.
+            let status = match StockStatus::from_site_text(&cells[1].text().collect::<String>()) {
+                StockStatus::InStock => StockStatus::MarketplaceAvailable,
+                other => other,
+            };
+            let (retailer, title) = split_retailer(&name_text);
             Some(GpuListing {
                 name: name_text,
+                retailer: retailer.or_else(|| Some("Ebay".to_string())),
+                product: ProductInfo::from_title(&title),
+                title,
                 status,
                 price: "-".to_string(),
                 price_numeric: None,
@@ -149,4 +471,142 @@ pub fn parse_listings(html_content: &str, quiet: bool) -> Result<Vec<GpuListing>
         }
     }
     Ok(listings)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(brand: Option<&str>, line: Option<&str>, variants: &[ProductVariant]) -> Option<ProductInfo> {
+        Some(ProductInfo {
+            brand: brand.map(str::to_string),
+            line: line.map(str::to_string),
+            variants: variants.to_vec(),
+        })
+    }
+
+    #[test]
+    fn status_text_maps_to_variants_and_keys_round_trip() {
+        let cases = [
+            ("In Stock", StockStatus::InStock, "in_stock"),
+            ("Preorder", StockStatus::Preorder, "preorder"),
+            ("Notify Me", StockStatus::NotifyMe, "notify_me"),
+            ("Stock Available", StockStatus::MarketplaceAvailable, "marketplace_available"),
+            ("Out of Stock", StockStatus::OutOfStock, "out_of_stock"),
+            ("Not Tracking", StockStatus::NotTracking, "not_tracking"),
+            ("Sold by 3rd party", StockStatus::Unknown("Sold by 3rd party".to_string()), "Sold by 3rd party"),
+        ];
+        for (text, status, key) in cases {
+            assert_eq!(StockStatus::from_site_text(text), status, "{}", text);
+            assert_eq!(status.key(), key);
+            assert_eq!(StockStatus::from_key(key), status);
+            assert_eq!(status.to_string(), text);
+            assert_eq!(serde_json::to_value(&status).unwrap(), key);
+            assert_eq!(serde_json::from_value::<StockStatus>(key.into()).unwrap(), status);
+        }
+    }
+
+    #[test]
+    fn status_text_is_matched_loosely() {
+        assert_eq!(StockStatus::from_site_text("  IN   stock\n"), StockStatus::InStock);
+        assert_eq!(StockStatus::from_site_text("Pre-Order"), StockStatus::Preorder);
+        assert_eq!(StockStatus::from_site_text("pre order"), StockStatus::Preorder);
+        // Unknown text is kept as shown, minus surrounding whitespace.
+        assert_eq!(StockStatus::from_site_text(" Back Soon "), StockStatus::Unknown("Back Soon".to_string()));
+    }
+
+    fn assert_split(name: &str, retailer: Option<&str>, title: &str) {
+        let (found, rest) = split_retailer(name);
+        assert_eq!((found.as_deref(), rest.as_str()), (retailer, title), "{:?}", name);
+    }
+
+    #[test]
+    fn splits_known_retailer_prefixes() {
+        assert_split("Best Buy : RTX 5090 Founders Edition", Some("Best Buy"), "RTX 5090 Founders Edition");
+        assert_split("  bestbuy - RTX 5090 FE ", Some("Best Buy"), "RTX 5090 FE");
+        assert_split("Nvidia Store : RTX 5090 FE", Some("Nvidia"), "RTX 5090 FE");
+        assert_split("B&H | MSI RTX 5080", Some("B&H"), "MSI RTX 5080");
+    }
+
+    #[test]
+    fn keeps_unknown_prefixes_in_the_title() {
+        assert_split("Costco : RTX 5090 Gaming OC", None, "Costco : RTX 5090 Gaming OC");
+    }
+
+    #[test]
+    fn needs_a_separator_after_the_retailer() {
+        assert_split("Nvidia GeForce RTX 5090 Founders Edition", None, "Nvidia GeForce RTX 5090 Founders Edition");
+        assert_split("ASUS TUF RTX 5090", None, "ASUS TUF RTX 5090");
+        // A bare retailer name keeps its name as the title.
+        assert_split("Newegg", Some("Newegg"), "Newegg");
+    }
+
+    #[test]
+    fn only_the_first_separator_is_split() {
+        assert_split("Newegg : ASUS RTX 5090 : OC Edition", Some("Newegg"), "ASUS RTX 5090 : OC Edition");
+    }
+
+    #[test]
+    fn recognizes_brand_line_and_variants() {
+        assert_eq!(
+            ProductInfo::from_title("ASUS ROG Astral GeForce RTX 5090 32GB GDDR7 OC Edition"),
+            info(Some("ASUS"), Some("ROG Astral"), &[ProductVariant::Oc]),
+        );
+        assert_eq!(
+            ProductInfo::from_title("MSI GeForce RTX 5080 16G Gaming X Trio White"),
+            info(Some("MSI"), Some("Gaming X Trio"), &[ProductVariant::White]),
+        );
+    }
+
+    #[test]
+    fn prefers_the_longest_line_name() {
+        assert_eq!(
+            ProductInfo::from_title("MSI Suprim Liquid SOC RTX 5090").and_then(|p| p.line),
+            Some("Suprim Liquid".to_string()),
+        );
+        assert_eq!(
+            ProductInfo::from_title("ZOTAC GAMING GeForce RTX 5090 AMP Extreme Infinity").and_then(|p| p.line),
+            Some("AMP Extreme Infinity".to_string()),
+        );
+    }
+
+    #[test]
+    fn matches_whole_words_only() {
+        // "oc" inside "Zotac" and "SOC" isn't the OC variant.
+        assert_eq!(
+            ProductInfo::from_title("Zotac GeForce RTX 5070 Solid SOC"),
+            info(Some("Zotac"), Some("Solid"), &[]),
+        );
+        assert_eq!(ProductInfo::from_title("Whitebox RTX 5070"), None);
+    }
+
+    #[test]
+    fn ignores_case_and_punctuation() {
+        assert_eq!(
+            ProductInfo::from_title("SAPPHIRE NITRO+ Radeon RX 9070 XT (OC)"),
+            info(Some("Sapphire"), Some("Nitro+"), &[ProductVariant::Oc]),
+        );
+        assert_eq!(
+            ProductInfo::from_title("Power Color Red Devil RX 9070 XT").and_then(|p| p.brand),
+            Some("PowerColor".to_string()),
+        );
+    }
+
+    #[test]
+    fn founders_edition_is_sold_by_nvidia() {
+        assert_eq!(
+            ProductInfo::from_title("NVIDIA GeForce RTX 5090 Founders Edition"),
+            info(Some("NVIDIA"), None, &[ProductVariant::FoundersEdition]),
+        );
+        // Without a Founders Edition, "NVIDIA" only names the chip.
+        assert_eq!(ProductInfo::from_title("NVIDIA GeForce RTX 5090"), None);
+    }
+
+    #[test]
+    fn variants_without_a_brand() {
+        assert_eq!(
+            ProductInfo::from_title("GeForce RTX 5080 Overclocked WHT"),
+            info(None, None, &[ProductVariant::Oc, ProductVariant::White]),
+        );
+    }
+}
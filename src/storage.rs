@@ -0,0 +1,220 @@
+use crate::cli::GpuModel;
+use crate::history::Observation;
+use crate::scraper::{GpuListing, ProductInfo, StockStatus};
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, Row, ToSql};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS observations (
+    id             INTEGER PRIMARY KEY,
+    model          TEXT NOT NULL,
+    listing_key    TEXT NOT NULL,
+    name           TEXT NOT NULL,
+    retailer       TEXT,
+    title          TEXT NOT NULL,
+    status         TEXT NOT NULL,
+    price          REAL,
+    price_text     TEXT NOT NULL,
+    last_available TEXT NOT NULL,
+    link           TEXT NOT NULL,
+    scraped_at     TEXT NOT NULL,
+    UNIQUE (model, listing_key, scraped_at)
+);
+CREATE INDEX IF NOT EXISTS observations_by_listing
+    ON observations (model, listing_key, scraped_at);
+CREATE INDEX IF NOT EXISTS observations_by_model
+    ON observations (model, scraped_at);
+CREATE INDEX IF NOT EXISTS observations_by_time
+    ON observations (scraped_at);
+";
+
+const COLUMNS: &str =
+    "model, name, retailer, title, status, price, price_text, last_available, link, scraped_at";
+
+/// SQLite-backed history of every listing observation, shared by the CLI,
+/// the web server, alerts and the email digest.
+///
+/// One row is stored per listing per scrape: the model, the listing's
+/// identity (link, or name when there is none), the listing as shown on the
+/// site, its status key (e.g. `in_stock`), numeric price and the time the page
+/// was fetched. Re-recording the same scrape (e.g. a cached page) is a no-op.
+///
+/// The database is opened (and created) on first use, so runs that never
+/// record or read history don't touch the disk. Calls block on SQLite, so
+/// async code runs them with `spawn_blocking`.
+pub struct Storage {
+    path: PathBuf,
+    conn: OnceLock<Mutex<Connection>>,
+}
+
+/// Scrape times are stored in one fixed-width format so they sort as text.
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+impl Storage {
+    pub fn new(path: PathBuf) -> Self {
+        Storage { path, conn: OnceLock::new() }
+    }
+
+    /// The connection, opening the database the first time it's needed.
+    fn conn(&self) -> Result<&Mutex<Connection>> {
+        if let Some(conn) = self.conn.get() {
+            return Ok(conn);
+        }
+        let conn = open(&self.path)?;
+        // If another thread got there first, its connection is used and this one dropped.
+        Ok(self.conn.get_or_init(|| Mutex::new(conn)))
+    }
+
+    /// Records one scrape of `model`. Returns the number of new rows.
+    pub fn record(&self, model: GpuModel, listings: &[GpuListing], scraped_at: DateTime<Utc>) -> Result<usize> {
+        let mut conn = self.conn()?.lock().expect("database lock poisoned");
+        let tx = conn.transaction().context("Failed to start database transaction")?;
+        let scraped_at = timestamp(scraped_at);
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO observations
+                    (model, listing_key, name, retailer, title, status, price, price_text, last_available, link, scraped_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for listing in listings {
+                inserted += stmt.execute(params![
+                    model.cli_name(),
+                    listing.identity(),
+                    listing.name,
+                    listing.retailer,
+                    listing.title,
+                    listing.status.key(),
+                    listing.price_numeric,
+                    listing.price,
+                    listing.last_available,
+                    listing.link,
+                    scraped_at,
+                ])?;
+            }
+        }
+        tx.commit().context("Failed to commit observations")?;
+        Ok(inserted)
+    }
+
+    /// Observations of `model` (or of every model), oldest first, limited to
+    /// scrapes at or after `since` when given.
+    pub fn observations(&self, model: Option<GpuModel>, since: Option<DateTime<Utc>>) -> Result<Vec<Observation>> {
+        let (filter, params) = filter(model, since.map(|since| (">=", since)));
+        self.query(&format!("SELECT {COLUMNS} FROM observations {filter} ORDER BY scraped_at, id"), &params)
+    }
+
+    /// Like [`Storage::observations`], plus each listing's last observation
+    /// before `since`, so changes at the start of the window show up in a
+    /// timeline.
+    pub fn observations_with_baseline(&self, model: Option<GpuModel>, since: DateTime<Utc>) -> Result<Vec<Observation>> {
+        let (filter, params) = filter(model, Some(("<", since)));
+        // SQLite takes the other columns of a MAX() aggregate from the row holding the maximum.
+        let mut observations = self.query(
+            &format!(
+                "SELECT {COLUMNS} FROM observations WHERE id IN (
+                    SELECT id FROM (SELECT id, MAX(scraped_at) FROM observations {filter} GROUP BY model, listing_key)
+                 ) ORDER BY scraped_at, id"
+            ),
+            &params,
+        )?;
+        observations.extend(self.observations(model, Some(since))?);
+        Ok(observations)
+    }
+
+    /// When `model` was last scraped and what that scrape saw.
+    pub fn latest_scrape(&self, model: GpuModel) -> Result<Option<(DateTime<Utc>, Vec<GpuListing>)>> {
+        let observations = self.query(
+            &format!(
+                "SELECT {COLUMNS} FROM observations WHERE model = ?1 AND scraped_at =
+                    (SELECT MAX(scraped_at) FROM observations WHERE model = ?1)
+                 ORDER BY id"
+            ),
+            &[model.cli_name().to_string()],
+        )?;
+        let Some(scraped_at) = observations.first().map(|o| o.scraped_at) else {
+            return Ok(None);
+        };
+        Ok(Some((scraped_at, observations.into_iter().map(|o| o.listing).collect())))
+    }
+
+    fn query(&self, sql: &str, params: &[String]) -> Result<Vec<Observation>> {
+        let conn = self.conn()?.lock().expect("database lock poisoned");
+        let mut stmt = conn.prepare_cached(sql).context("Failed to query observations")?;
+        let params: Vec<&dyn ToSql> = params.iter().map(|p| p as &dyn ToSql).collect();
+        let rows = stmt.query_map(params.as_slice(), read_row).context("Failed to query observations")?;
+        let mut observations = Vec::new();
+        for row in rows {
+            // Rows for models this version doesn't know are skipped.
+            if let Some(observation) = row.context("Failed to read observation")? {
+                observations.push(observation);
+            }
+        }
+        Ok(observations)
+    }
+}
+
+fn open(path: &Path) -> Result<Connection> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create database directory {}", parent.display()))?;
+    }
+    let conn = Connection::open(path)
+        .with_context(|| format!("Failed to open database {}", path.display()))?;
+    // The CLI and a running web server may write at the same time.
+    conn.busy_timeout(Duration::from_secs(10))?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.execute_batch(SCHEMA)
+        .with_context(|| format!("Failed to initialize database {}", path.display()))?;
+    Ok(conn)
+}
+
+/// A WHERE clause selecting `model` (if given) and scrapes compared to a time.
+fn filter(model: Option<GpuModel>, time: Option<(&str, DateTime<Utc>)>) -> (String, Vec<String>) {
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    if let Some(model) = model {
+        params.push(model.cli_name().to_string());
+        conditions.push(format!("model = ?{}", params.len()));
+    }
+    if let Some((op, time)) = time {
+        params.push(timestamp(time));
+        conditions.push(format!("scraped_at {} ?{}", op, params.len()));
+    }
+    if conditions.is_empty() {
+        (String::new(), params)
+    } else {
+        (format!("WHERE {}", conditions.join(" AND ")), params)
+    }
+}
+
+fn read_row(row: &Row) -> rusqlite::Result<Option<Observation>> {
+    let model: String = row.get("model")?;
+    let Ok(model) = model.parse::<GpuModel>() else {
+        return Ok(None);
+    };
+    let scraped_at: String = row.get("scraped_at")?;
+    let scraped_at = DateTime::parse_from_rfc3339(&scraped_at)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+    let title: String = row.get("title")?;
+    let status: String = row.get("status")?;
+    let listing = GpuListing {
+        name: row.get("name")?,
+        retailer: row.get("retailer")?,
+        product: ProductInfo::from_title(&title),
+        title,
+        status: StockStatus::from_key(&status),
+        price: row.get("price_text")?,
+        price_numeric: row.get("price")?,
+        last_available: row.get("last_available")?,
+        link: row.get("link")?,
+    };
+    Ok(Some(Observation { scraped_at, model, listing }))
+}
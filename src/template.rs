@@ -0,0 +1,231 @@
+use crate::output::ListingRecord;
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use serde_json::Value;
+use std::path::Path;
+
+/// Fields a `--template` can use: the NDJSON record fields (nested ones dotted)
+/// plus `status_text`.
+const FIELDS: &[&str] = &[
+    "model",
+    "scraped_at",
+    "name",
+    "retailer",
+    "title",
+    "product.brand",
+    "product.line",
+    "product.variants",
+    "status",
+    "status_text",
+    "price",
+    "price_numeric",
+    "last_available",
+    "link",
+];
+
+/// Most decimals `{{price_numeric:.N}}` accepts; `format!` panics on large precisions.
+const MAX_DECIMALS: usize = 20;
+
+enum Format {
+    Decimals(usize),
+    Time(String),
+}
+
+enum Part {
+    Text(String),
+    Field { name: String, format: Option<Format> },
+}
+
+/// A user-supplied output template (`--template`), rendered once per listing.
+///
+/// `{{field}}` is replaced with the listing's value, or nothing when the
+/// listing doesn't have one. `{{price_numeric:.0}}` sets the number of
+/// decimals, and `{{scraped_at:%H:%M}}` formats the scrape time (in local time)
+/// with a strftime pattern.
+pub struct UserTemplate {
+    parts: Vec<Part>,
+}
+
+impl UserTemplate {
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template {}", path.display()))?;
+        // Each listing's output ends with one newline, whether or not the file does.
+        let source = source.strip_suffix('\n').unwrap_or(&source);
+        let source = source.strip_suffix('\r').unwrap_or(source);
+        Self::parse(source).with_context(|| format!("Invalid template {}", path.display()))
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let Some(len) = rest[start + 2..].find("}}") else {
+                bail!("Unclosed {{{{ in: {}", &rest[start..]);
+            };
+            let inner = rest[start + 2..start + 2 + len].trim();
+            let (name, spec) = match inner.split_once(':') {
+                Some((name, spec)) => (name.trim(), Some(spec.to_string())),
+                None => (inner, None),
+            };
+            if !FIELDS.contains(&name) {
+                bail!("Unknown template field {{{{{}}}}} (available: {})", name, FIELDS.join(", "));
+            }
+            let format = match (name, spec) {
+                (_, None) => None,
+                ("scraped_at", Some(pattern)) => {
+                    let invalid = chrono::format::StrftimeItems::new(&pattern)
+                        .any(|item| matches!(item, chrono::format::Item::Error));
+                    if invalid {
+                        bail!("Invalid time format for scraped_at: {}", pattern);
+                    }
+                    Some(Format::Time(pattern))
+                }
+                ("price_numeric", Some(spec)) => {
+                    let Some(decimals) = spec.strip_prefix('.').and_then(|d| d.parse::<usize>().ok()) else {
+                        bail!("Invalid format for price_numeric: {} (expected e.g. .2)", spec);
+                    };
+                    if decimals > MAX_DECIMALS {
+                        bail!("Too many decimals for price_numeric: {} (at most {})", decimals, MAX_DECIMALS);
+                    }
+                    Some(Format::Decimals(decimals))
+                }
+                (name, Some(_)) => bail!("{} doesn't take a format (only price_numeric and scraped_at do)", name),
+            };
+            parts.push(Part::Field { name: name.to_string(), format });
+            rest = &rest[start + 2 + len + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        Ok(UserTemplate { parts })
+    }
+
+    pub fn render(&self, record: &ListingRecord) -> Result<String> {
+        let value = serde_json::to_value(record).context("Failed to serialize listing")?;
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Field { name, format } => out.push_str(&field(record, &value, name, format.as_ref())),
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn field(record: &ListingRecord, value: &Value, name: &str, format: Option<&Format>) -> String {
+    match (name, format) {
+        ("status_text", _) => record.listing.status.to_string(),
+        (_, Some(Format::Time(pattern))) => record.scraped_at.with_timezone(&Local).format(pattern).to_string(),
+        (_, Some(Format::Decimals(decimals))) => {
+            record.listing.price_numeric.map(|p| format!("{:.*}", decimals, p)).unwrap_or_default()
+        }
+        (_, None) => match name.split('.').try_fold(value, |value, key| value.get(key)) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|item| item.as_str().map_or_else(|| item.to_string(), str::to_string))
+                .collect::<Vec<_>>()
+                .join(","),
+            Some(other) => other.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::GpuModel;
+    use crate::scraper::{GpuListing, ProductInfo, ProductVariant, StockStatus};
+    use chrono::{TimeZone, Utc};
+
+    fn listing(price_numeric: Option<f64>) -> GpuListing {
+        GpuListing {
+            product: Some(ProductInfo {
+                brand: Some("ASUS".to_string()),
+                line: Some("TUF Gaming".to_string()),
+                variants: vec![ProductVariant::Oc, ProductVariant::White],
+            }),
+            ..GpuListing::for_test("5090", StockStatus::InStock, price_numeric)
+        }
+    }
+
+    fn render(source: &str, listing: &GpuListing) -> String {
+        let record = ListingRecord {
+            model: GpuModel::Rtx5090,
+            scraped_at: Utc.with_ymd_and_hms(2026, 6, 15, 12, 0, 0).unwrap(),
+            listing,
+        };
+        UserTemplate::parse(source).unwrap().render(&record).unwrap()
+    }
+
+    fn parse_error(source: &str) -> String {
+        match UserTemplate::parse(source) {
+            Ok(_) => panic!("{:?} should not parse", source),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn renders_fields_and_text() {
+        assert_eq!(
+            render("{{model}} | {{ retailer }} | {{status}} ({{status_text}}) {{price}}", &listing(Some(2199.99))),
+            "5090 | Best Buy | in_stock (In Stock) $2199.99",
+        );
+        assert_eq!(render("no fields", &listing(None)), "no fields");
+    }
+
+    #[test]
+    fn renders_nested_and_list_fields() {
+        assert_eq!(
+            render("{{product.brand}}/{{product.line}}/{{product.variants}}", &listing(None)),
+            "ASUS/TUF Gaming/oc,white",
+        );
+    }
+
+    #[test]
+    fn missing_values_render_empty() {
+        let mut bare = listing(None);
+        bare.retailer = None;
+        bare.product = None;
+        assert_eq!(
+            render("[{{retailer}}][{{product.brand}}][{{price_numeric}}][{{price_numeric:.0}}]", &bare),
+            "[][][][]",
+        );
+    }
+
+    #[test]
+    fn applies_formats() {
+        assert_eq!(render("{{price_numeric}} {{price_numeric:.0}}", &listing(Some(2199.5))), "2199.5 2200");
+        assert_eq!(render("{{price_numeric:.20}}", &listing(Some(1.5))), "1.50000000000000000000");
+        assert_eq!(render("{{scraped_at:%Y-%m}}", &listing(None)), "2026-06");
+        assert_eq!(render("{{scraped_at}}", &listing(None)), "2026-06-15T12:00:00Z");
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(parse_error("{{brand}}").starts_with("Unknown template field {{brand}}"));
+    }
+
+    #[test]
+    fn rejects_unclosed_braces() {
+        assert_eq!(parse_error("{{model}} {{price"), "Unclosed {{ in: {{price");
+    }
+
+    #[test]
+    fn rejects_bad_formats() {
+        assert!(parse_error("{{price_numeric:2}}").starts_with("Invalid format for price_numeric: 2"));
+        assert!(parse_error("{{price_numeric:.x}}").starts_with("Invalid format for price_numeric"));
+        assert_eq!(parse_error("{{price_numeric:.70000}}"), "Too many decimals for price_numeric: 70000 (at most 20)");
+        assert_eq!(parse_error("{{scraped_at:%Q}}"), "Invalid time format for scraped_at: %Q");
+        assert_eq!(
+            parse_error("{{title:.2}}"),
+            "title doesn't take a format (only price_numeric and scraped_at do)",
+        );
+    }
+}
@@ -1,17 +1,27 @@
-use crate::cli::GpuModel;
-use crate::scraper::{self, GpuListing, USER_AGENT};
+use crate::alerts::AlertEngine;
+use crate::cache::PageCache;
+use crate::cassette::Cassette;
+use crate::cli::{Args, GpuModel};
+use crate::fetcher::{FetchedPage, Fetcher, ModelFetchReport};
+use crate::history::{self, ModelHistory};
+use crate::poller::{self, PollerConfig, SnapshotStore};
+use crate::output;
+use crate::scraper::{self, GpuListing};
+use crate::recorder::ScrapeRecorder;
+use crate::storage::Storage;
 use anyhow::{Context, Result};
 use askama::Template;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::{Html, IntoResponse, Response},
+    response::{Html, IntoResponse, Json, Response},
     routing::{get, get_service},
     Router,
 };
 use clap::ValueEnum;
 use futures::future::join_all;
-use std::{net::SocketAddr, sync::Arc};
+use serde::Deserialize;
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use tower_http::services::ServeDir;
 use anyhow::anyhow;
 
@@ -23,20 +33,146 @@ struct IndexTemplate {
     models: Vec<GpuModel>,
     current_model: Option<GpuModel>,
     last_updated: String,
+    /// Models that failed to load or needed retries.
+    fetch_notes: Vec<String>,
 }
 
-#[derive(Clone)]
-struct AppState {}
+#[derive(Template)]
+#[template(path = "history.html")]
+struct HistoryTemplate {
+    title: String,
+    models: Vec<GpuModel>,
+    current_model: GpuModel,
+}
+
+/// Where listings pages come from: the live site or pages saved to disk.
+#[derive(Debug, Clone)]
+pub enum HtmlSource {
+    /// Fetch from nowinstock.
+    Live,
+    /// Serve responses previously recorded with `--record`.
+    Replay(Cassette),
+    /// A single saved page, used for every requested model.
+    File(PathBuf),
+    /// A directory with one saved page per model.
+    Dir(PathBuf),
+}
+
+impl HtmlSource {
+    pub fn from_args(args: &Args) -> Self {
+        if let Some(path) = &args.from_file {
+            HtmlSource::File(path.clone())
+        } else if let Some(dir) = &args.from_dir {
+            HtmlSource::Dir(dir.clone())
+        } else if let Some(dir) = &args.replay {
+            HtmlSource::Replay(Cassette::new(dir))
+        } else {
+            HtmlSource::Live
+        }
+    }
 
-struct AppError(anyhow::Error);
+    /// True for `--from-file`/`--from-dir`/`--replay`, whose pages were saved
+    /// earlier and aren't fresh observations.
+    pub fn is_saved_page(&self) -> bool {
+        !matches!(self, HtmlSource::Live)
+    }
+
+    /// Loads the listings page HTML for `model`, using `fetcher` for live requests.
+    pub async fn load(&self, fetcher: &Fetcher, model: GpuModel, quiet: bool) -> Result<FetchedPage> {
+        match self {
+            HtmlSource::Live => fetcher.fetch_html(&scraper::model_url(model), quiet).await,
+            HtmlSource::Replay(cassette) => replay_html(cassette, &scraper::model_url(model), quiet).await,
+            HtmlSource::File(path) => read_saved_page(path, quiet).await,
+            HtmlSource::Dir(dir) => {
+                // Accept both the CLI name ("5090.html") and the URL slug ("rtx5090.html").
+                let candidates = [
+                    dir.join(format!("{}.html", model.cli_name())),
+                    dir.join(format!("{}.html", model.to_string().trim_end_matches('/'))),
+                ];
+                let path = candidates
+                    .iter()
+                    .find(|p| p.is_file())
+                    .ok_or_else(|| anyhow!(
+                        "No saved page for {:?} in {} (expected {} or {})",
+                        model,
+                        dir.display(),
+                        candidates[0].display(),
+                        candidates[1].display()
+                    ))?;
+                read_saved_page(path, quiet).await
+            }
+        }
+    }
+}
+
+async fn read_saved_page(path: &std::path::Path, quiet: bool) -> Result<FetchedPage> {
+    if !quiet {
+        println!("Reading saved page: {}", path.display());
+    }
+    let body = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read saved page {}", path.display()))?;
+    Ok(FetchedPage { body, attempts: 1, fetched_at: chrono::Local::now() })
+}
+
+/// Web server settings taken from the command line.
+pub struct ServerConfig {
+    pub listen: SocketAddr,
+    /// TTL of the on-demand page cache.
+    pub cache_ttl: Duration,
+    /// Background refresh settings; `None` fetches on demand when pages are viewed.
+    pub poller: Option<PollerConfig>,
+}
+
+impl ServerConfig {
+    pub fn from_args(args: &Args) -> Self {
+        let poller = (args.refresh_interval > 0).then(|| PollerConfig {
+            default_interval: Duration::from_secs(args.refresh_interval),
+            model_intervals: args
+                .model_interval
+                .iter()
+                .map(|(model, secs)| (*model, Duration::from_secs(*secs)))
+                .collect(),
+        });
+        ServerConfig {
+            listen: args.listen,
+            cache_ttl: Duration::from_secs(args.cache_ttl),
+            poller,
+        }
+    }
+}
+
+struct AppState {
+    fetcher: Fetcher,
+    source: HtmlSource,
+    /// Shared by page views and the background poller.
+    cache: Arc<PageCache>,
+    /// Latest listings kept fresh by the background poller, when enabled.
+    snapshots: Option<Arc<SnapshotStore>>,
+    /// Records every fresh scrape to `storage`.
+    recorder: ScrapeRecorder,
+    /// Evaluated against every fresh scrape.
+    alerts: AlertEngine,
+    /// Read by the history pages, even when this server isn't recording to it.
+    storage: Arc<Storage>,
+}
+
+enum AppError {
+    /// The request itself was invalid (e.g. a malformed query parameter).
+    BadRequest(String),
+    Internal(anyhow::Error),
+}
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Something went wrong: {}", self.0),
-        )
-            .into_response()
+        match self {
+            AppError::BadRequest(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+            AppError::Internal(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Something went wrong: {}", e),
+            )
+                .into_response(),
+        }
     }
 }
 
@@ -45,38 +181,70 @@ where
     E: Into<anyhow::Error>,
 {
     fn from(err: E) -> Self {
-        Self(err.into())
+        Self::Internal(err.into())
+    }
+}
+
+/// One model's listings as rendered on a page, from either the poller's
+/// snapshot or an on-demand fetch.
+struct ModelView {
+    listings: Vec<GpuListing>,
+    fetched_at: Option<chrono::DateTime<chrono::Local>>,
+    /// Shown on the page when the data is missing, stale or needed retries.
+    note: Option<String>,
+    /// Set when there is nothing to show because loading failed.
+    error: Option<anyhow::Error>,
+}
+
+async fn model_view(state: &AppState, model: GpuModel) -> ModelView {
+    // Until the poller's first refresh of a model finishes, load it on demand
+    // (through the cache, so this shares a fetch that's already in flight).
+    let snapshot = state.snapshots.as_ref().map(|snapshots| snapshots.get(model));
+    if let Some(snapshot) = snapshot.filter(|snapshot| snapshot.last_attempt.is_some()) {
+        return ModelView {
+            note: snapshot.status_note(model),
+            listings: snapshot.listings,
+            fetched_at: snapshot.fetched_at,
+            error: None,
+        };
+    }
+    let (result, report, fetched_at) = fetch_and_parse(state, model).await;
+    let note = report.is_notable().then(|| report.summary());
+    match result {
+        Ok(listings) => ModelView { listings, fetched_at, note, error: None },
+        Err(e) => ModelView { listings: Vec::new(), fetched_at, note, error: Some(e) },
     }
 }
 
 // Handler for the home page (all GPUs)
-async fn home_handler(State(_state): State<Arc<AppState>>) -> impl IntoResponse {
+async fn home_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let models_to_check = GpuModel::value_variants();
-    let fetch_futures = models_to_check.iter().map(|model| async move {
-        let model_url = format!("{}{}", scraper::get_base_url(*model), model);
+    let state = &state;
+    let view_futures = models_to_check.iter().map(|model| async move {
         // Errors fetching/parsing a single model result in an empty list for that model,
         // allowing the page to still load with data from other models.
-        match fetch_and_parse(&model_url).await {
-            Ok(listings) => Ok::<(GpuModel, Vec<GpuListing>), anyhow::Error>((*model, listings)),
-            Err(e) => {
-                // Log the error server-side but don't fail the whole request
-                eprintln!("Failed to fetch/parse listings for {:?}: {}", model, e);
-                Ok::<(GpuModel, Vec<GpuListing>), anyhow::Error>((*model, Vec::new()))
-            },
+        let view = model_view(state, *model).await;
+        if let Some(e) = &view.error {
+            // Log the error server-side but don't fail the whole request
+            eprintln!("Failed to fetch/parse listings for {:?}: {}", model, e);
         }
+        view
     });
-    let results: Vec<Result<(GpuModel, Vec<GpuListing>), _>> = join_all(fetch_futures).await;
-    let all_listings: Vec<GpuListing> = results
+    let views: Vec<ModelView> = join_all(view_futures).await;
+    let fetch_notes: Vec<String> = views.iter().filter_map(|view| view.note.clone()).collect();
+    // The page is only as fresh as its oldest data.
+    let oldest_fetch = views.iter().filter_map(|view| view.fetched_at).min();
+    let all_listings: Vec<GpuListing> = views
         .into_iter()
-        .filter_map(|res| res.ok()) // Filter out errors here
-        .flat_map(|(_, listings)| listings)
+        .flat_map(|view| view.listings)
         .collect();
     let template = IndexTemplate {
         title: "All GPU Listings".to_string(),
         listings: all_listings,
         models: models_to_check.to_vec(),
         current_model: None,
-        last_updated: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        last_updated: format_fetched_at(oldest_fetch),
+        fetch_notes,
     };
     // Render template or return error string
     match template.render() {
@@ -91,66 +259,181 @@ async fn home_handler(State(_state): State<Arc<AppState>>) -> impl IntoResponse
 
 // Handler for individual GPU model pages
 async fn gpu_model_handler(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
     Path(model_str): Path<String>,
 ) -> Result<Html<String>, AppError> { // Return Result using AppError
-    let model: GpuModel = model_str.parse()
-        // Use map_err to convert the parsing error into AppError
-        .map_err(|_| AppError(anyhow!("Invalid GPU model specified: {}", model_str)))?;
-    let model_url = format!("{}{}", scraper::get_base_url(model), model);
-    // Use `?` to propagate errors from fetch_and_parse, automatically converting them to AppError
-    let listings = fetch_and_parse(&model_url).await?;
+    let model = parse_model(&model_str)?;
+    let view = model_view(&state, model).await;
+    // Use `?` to propagate load errors, automatically converting them to AppError
+    if let Some(e) = view.error {
+        return Err(e.into());
+    }
     let template = IndexTemplate {
         title: format!("{:?} Listings", model), // Use Debug format for enum
-        listings,
+        listings: view.listings,
         models: GpuModel::value_variants().to_vec(),
         current_model: Some(model),
-        last_updated: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        last_updated: format_fetched_at(view.fetched_at),
+        fetch_notes: view.note.into_iter().collect(),
     };
     // Render the template, converting template errors into AppError using `?`
     let html_output = template.render()
-        .map_err(|e| AppError(anyhow!(e).context("Template rendering failed")))?;
+        .map_err(|e| AppError::Internal(anyhow!(e).context("Template rendering failed")))?;
     Ok(Html(html_output)) // Return Ok(Html(...)) on success
 }
 
-async fn fetch_and_parse(url: &str) -> Result<Vec<GpuListing>> {
-    let html = fetch_html(url, false).await?;
-    scraper::parse_listings(&html, false)
+fn parse_model(model_str: &str) -> Result<GpuModel, AppError> {
+    model_str
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("Invalid GPU model specified: {}", model_str)))
 }
 
-pub async fn fetch_html(url: &str, quiet: bool) -> Result<String> {
-    if !quiet {
-        println!("Fetching URL: {}", url);
-    }
-    let client = reqwest::Client::builder()
-        .danger_accept_invalid_certs(true)
-        .http1_only()
-        .user_agent(USER_AGENT)
-        .timeout(std::time::Duration::from_secs(15))
-        .build()?;
-
-    let response = client.get(url).send().await.context("Failed to send request")?;
-
-    if !response.status().is_success() {
-        anyhow::bail!(
-            "Request failed with status: {} for URL: {}",
-            response.status(),
-            url
-        );
+// Handler for a model's price history page; the charts load their data from history.json.
+async fn history_page_handler(Path(model_str): Path<String>) -> Result<Html<String>, AppError> {
+    let model = parse_model(&model_str)?;
+    let template = HistoryTemplate {
+        title: format!("{:?} Price History", model),
+        models: GpuModel::value_variants().to_vec(),
+        current_model: model,
+    };
+    let html_output = template.render()
+        .map_err(|e| AppError::Internal(anyhow!(e).context("Template rendering failed")))?;
+    Ok(Html(html_output))
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    /// Look-back window such as 24h or 30d; defaults to a week.
+    since: Option<String>,
+}
+
+// Handler for the chart data behind the history page.
+async fn history_json_handler(
+    State(state): State<Arc<AppState>>,
+    Path(model_str): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<ModelHistory>, AppError> {
+    let model = parse_model(&model_str)?;
+    let window = match query.since.as_deref() {
+        Some("all") => None,
+        Some(since) => Some(crate::cli::parse_since(since).map_err(AppError::BadRequest)?),
+        None => Some(chrono::Duration::days(7)),
+    };
+    let since = window.map(|window| chrono::Utc::now() - window);
+    let storage = state.storage.clone();
+    let observations = tokio::task::spawn_blocking(move || storage.observations(Some(model), since))
+        .await
+        .context("History database reader panicked")??;
+    Ok(Json(history::model_history(model, &observations, since)))
+}
+
+/// Formats a fetch time for the "Last Updated" line.
+fn format_fetched_at(fetched_at: Option<chrono::DateTime<chrono::Local>>) -> String {
+    fetched_at
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "never".to_string())
+}
+
+/// Loads (through the cache) and parses one model's page. Also returns the
+/// fetch report and when the underlying page was fetched, if it was.
+async fn fetch_and_parse(
+    state: &AppState,
+    model: GpuModel,
+) -> (Result<Vec<GpuListing>>, ModelFetchReport, Option<chrono::DateTime<chrono::Local>>) {
+    let url = scraper::model_url(model);
+    let load = state.cache.get_or_fetch(&url, || state.source.load(&state.fetcher, model, false));
+    let (page, fresh) = match load.await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            let report = ModelFetchReport::failure(model, &e);
+            return (Err(e), report, None);
+        }
+    };
+    match scraper::parse_listings(&page.body, false) {
+        Ok(listings) => {
+            // Pages served from the cache were already recorded when fetched.
+            if fresh {
+                if let Err(e) = state.recorder.record(model, &listings, page.fetched_at).await {
+                    eprintln!("Failed to record observations for {:?}: {:#}", model, e);
+                }
+                output::print_alerts(&state.alerts.check(model, &listings, page.fetched_at));
+            }
+            (Ok(listings), ModelFetchReport::success(model, page.attempts), Some(page.fetched_at))
+        }
+        Err(e) => {
+            let report = ModelFetchReport::failure(model, &e);
+            (Err(e), report, Some(page.fetched_at))
+        }
     }
+}
 
-    response.text().await.context("Failed to read response text")
+async fn replay_html(cassette: &Cassette, url: &str, quiet: bool) -> Result<FetchedPage> {
+    let (meta, body) = cassette.replay(url).await?;
+    if !quiet {
+        println!("Replaying {} (recorded {})", url, meta.recorded_at);
+    }
+    let status = StatusCode::from_u16(meta.status)
+        .with_context(|| format!("Invalid recorded status {} for {}", meta.status, url))?;
+    if !status.is_success() {
+        anyhow::bail!("Request failed with status: {} for URL: {}", status, url);
+    }
+    let fetched_at = chrono::DateTime::parse_from_rfc3339(&meta.recorded_at)
+        .map(|t| t.with_timezone(&chrono::Local))
+        .unwrap_or_else(|_| chrono::Local::now());
+    Ok(FetchedPage { body, attempts: 1, fetched_at })
 }
 
-pub async fn run_server(listen_addr: SocketAddr) -> Result<()> {
+pub async fn run_server(
+    config: ServerConfig,
+    fetcher: Fetcher,
+    source: HtmlSource,
+    storage: Arc<Storage>,
+    recorder: ScrapeRecorder,
+    alerts: AlertEngine,
+) -> Result<()> {
     use chrono::Local;
     use axum::extract::ConnectInfo;
     use std::net::SocketAddr as StdSocketAddr;
+    let listen_addr = config.listen;
     println!("Listening on http://{}", listen_addr);
-    let state = Arc::new(AppState {});
+    if !matches!(source, HtmlSource::Live) {
+        println!("Serving listings from saved pages: {:?}", source);
+    }
+    if !alerts.is_empty() {
+        println!("Evaluating alert rules on every refresh");
+    }
+    let cache = Arc::new(PageCache::new(config.cache_ttl));
+    let snapshots = config.poller.map(|poller_config| {
+        println!(
+            "Refreshing listings in the background every {}s",
+            poller_config.default_interval.as_secs()
+        );
+        let store = Arc::new(SnapshotStore::default());
+        poller::spawn(
+            fetcher.clone(),
+            source.clone(),
+            cache.clone(),
+            store.clone(),
+            recorder.clone(),
+            alerts.clone(),
+            poller_config,
+        );
+        store
+    });
+    let state = Arc::new(AppState {
+        fetcher,
+        source,
+        cache,
+        snapshots,
+        recorder,
+        alerts,
+        storage,
+    });
     let app = Router::new()
         .route("/", get(home_handler))
         .route("/gpu/:model", get(gpu_model_handler))
+        .route("/gpu/:model/history", get(history_page_handler))
+        .route("/gpu/:model/history.json", get(history_json_handler))
         .nest_service("/static", get_service(ServeDir::new("static")))
         .with_state(state)
         .layer(axum::middleware::from_fn(|req: axum::http::Request<axum::body::Body>, next: axum::middleware::Next| {
@@ -0,0 +1,201 @@
+// Minimal SVG charts for the price history page. Self-hosted so the history
+// view works without any CDN.
+(function (global) {
+	"use strict";
+
+	var SVG_NS = "http://www.w3.org/2000/svg";
+	var WIDTH = 960;
+	var MARGIN = { top: 16, right: 16, bottom: 28, left: 72 };
+
+	function el(name, attrs, parent) {
+		var node = document.createElementNS(SVG_NS, name);
+		Object.keys(attrs || {}).forEach(function (key) {
+			node.setAttribute(key, attrs[key]);
+		});
+		if (parent) {
+			parent.appendChild(node);
+		}
+		return node;
+	}
+
+	function text(parent, x, y, content, attrs) {
+		var node = el("text", Object.assign({ x: x, y: y }, attrs || {}), parent);
+		node.textContent = content;
+		return node;
+	}
+
+	function svgRoot(container, height) {
+		container.innerHTML = "";
+		return el(
+			"svg",
+			{ viewBox: "0 0 " + WIDTH + " " + height, preserveAspectRatio: "xMidYMid meet" },
+			container
+		);
+	}
+
+	function showEmpty(container, message) {
+		container.innerHTML = "";
+		var p = document.createElement("p");
+		p.className = "empty";
+		p.textContent = message;
+		container.appendChild(p);
+	}
+
+	function scale(domainMin, domainMax, rangeMin, rangeMax) {
+		var span = domainMax - domainMin || 1;
+		return function (value) {
+			return rangeMin + ((value - domainMin) / span) * (rangeMax - rangeMin);
+		};
+	}
+
+	// Roughly `count` evenly spaced ticks between min and max on "nice" steps.
+	function niceTicks(min, max, count) {
+		var span = max - min || Math.abs(max) || 1;
+		var step = Math.pow(10, Math.floor(Math.log10(span / count)));
+		var error = (count * step) / span;
+		if (error <= 0.15) step *= 10;
+		else if (error <= 0.35) step *= 5;
+		else if (error <= 0.75) step *= 2;
+		var ticks = [];
+		for (var v = Math.ceil(min / step) * step; v <= max + step / 2; v += step) {
+			ticks.push(v);
+		}
+		return ticks;
+	}
+
+	function formatTime(ms, span) {
+		var d = new Date(ms);
+		var pad = function (n) {
+			return n < 10 ? "0" + n : "" + n;
+		};
+		var date = d.getFullYear() + "-" + pad(d.getMonth() + 1) + "-" + pad(d.getDate());
+		var time = pad(d.getHours()) + ":" + pad(d.getMinutes());
+		if (span < 36 * 3600 * 1000) return time;
+		if (span < 7 * 24 * 3600 * 1000) return date.slice(5) + " " + time;
+		return date;
+	}
+
+	function timeAxis(svg, x, from, to, top, bottom) {
+		var axis = el("g", { class: "axis" }, svg);
+		var ticks = 6;
+		for (var i = 0; i <= ticks; i++) {
+			var t = from + ((to - from) * i) / ticks;
+			var px = x(t);
+			el("line", { x1: px, x2: px, y1: top, y2: bottom }, axis);
+			text(axis, px, bottom + 18, formatTime(t, to - from), { "text-anchor": "middle" });
+		}
+	}
+
+	function legend(svg, series) {
+		var group = el("g", { class: "legend" }, svg);
+		var x = MARGIN.left + 8;
+		series.forEach(function (s) {
+			el("rect", { x: x, y: MARGIN.top - 2, width: 12, height: 12, fill: s.color }, group);
+			text(group, x + 16, MARGIN.top + 8, s.label);
+			x += 24 + s.label.length * 7;
+		});
+	}
+
+	// series: [{ label, color, points: [[timeMs, value or null], ...] }]
+	// A null value leaves a gap in the line.
+	function line(container, options) {
+		var height = options.height || 320;
+		var series = options.series;
+		var format = options.format || String;
+		var all = [];
+		series.forEach(function (s) {
+			s.points.forEach(function (p) {
+				if (p[1] !== null) all.push(p);
+			});
+		});
+		if (all.length === 0) {
+			showEmpty(container, options.empty || "No data.");
+			return;
+		}
+		var times = all.map(function (p) { return p[0]; });
+		var values = all.map(function (p) { return p[1]; });
+		var from = Math.min.apply(null, times);
+		var to = Math.max.apply(null, times);
+		var ticks = niceTicks(Math.min.apply(null, values), Math.max.apply(null, values), 5);
+		var yMin = ticks[0];
+		var yMax = ticks[ticks.length - 1];
+
+		var svg = svgRoot(container, height);
+		var x = scale(from, to, MARGIN.left, WIDTH - MARGIN.right);
+		var y = scale(yMin, yMax, height - MARGIN.bottom, MARGIN.top + 16);
+
+		var grid = el("g", { class: "axis" }, svg);
+		ticks.forEach(function (v) {
+			el("line", { x1: MARGIN.left, x2: WIDTH - MARGIN.right, y1: y(v), y2: y(v) }, grid);
+			text(grid, MARGIN.left - 6, y(v) + 4, format(v), { "text-anchor": "end" });
+		});
+		timeAxis(svg, x, from, to, MARGIN.top + 16, height - MARGIN.bottom);
+
+		series.forEach(function (s) {
+			var d = "";
+			var penDown = false;
+			s.points.forEach(function (p) {
+				if (p[1] === null) {
+					penDown = false;
+					return;
+				}
+				d += (penDown ? "L" : "M") + x(p[0]).toFixed(1) + " " + y(p[1]).toFixed(1);
+				penDown = true;
+			});
+			el("path", { d: d, fill: "none", stroke: s.color, "stroke-width": 2 }, svg);
+			s.points.forEach(function (p) {
+				if (p[1] === null) return;
+				var dot = el("circle", { cx: x(p[0]), cy: y(p[1]), r: 2.5, fill: s.color }, svg);
+				el("title", {}, dot).textContent =
+					s.label + ": " + format(p[1]) + " (" + new Date(p[0]).toLocaleString() + ")";
+			});
+		});
+		legend(svg, series);
+	}
+
+	// rows: [{ label, intervals: [[startMs, endMs, open], ...] }] drawn as
+	// horizontal bars between `from` and `to`.
+	function timeline(container, options) {
+		var rows = options.rows;
+		if (rows.length === 0) {
+			showEmpty(container, options.empty || "No data.");
+			return;
+		}
+		var rowHeight = 24;
+		var height = MARGIN.top + rows.length * rowHeight + MARGIN.bottom;
+		var svg = svgRoot(container, height);
+		var from = options.from;
+		var to = Math.max(options.to, from + 1);
+		var x = scale(from, to, MARGIN.left + 48, WIDTH - MARGIN.right);
+		timeAxis(svg, x, from, to, MARGIN.top, height - MARGIN.bottom);
+
+		var labels = el("g", { class: "axis" }, svg);
+		rows.forEach(function (row, i) {
+			var top = MARGIN.top + i * rowHeight;
+			text(labels, MARGIN.left + 42, top + rowHeight / 2 + 4, row.label, { "text-anchor": "end" });
+			row.intervals.forEach(function (interval) {
+				var x1 = x(interval[0]);
+				// Single-scrape intervals still get a visible sliver.
+				var width = Math.max(x(interval[1]) - x1, 3);
+				var bar = el(
+					"rect",
+					{
+						x: x1,
+						y: top + 4,
+						width: width,
+						height: rowHeight - 8,
+						rx: 3,
+						fill: options.color || "#28a745",
+						"fill-opacity": interval[2] ? 1 : 0.8,
+					},
+					svg
+				);
+				el("title", {}, bar).textContent =
+					row.label + ": " + new Date(interval[0]).toLocaleString() + " – " +
+					(interval[2] ? "now" : new Date(interval[1]).toLocaleString());
+			});
+		});
+	}
+
+	global.MiniChart = { line: line, timeline: timeline };
+})(window);
@@ -0,0 +1,84 @@
+// Loads a model's history JSON and draws the price and availability charts.
+(function () {
+	"use strict";
+
+	var root = document.getElementById("history");
+	var model = root.dataset.model;
+	var status = document.getElementById("historyStatus");
+
+	function money(value) {
+		return "$" + value.toLocaleString(undefined, { maximumFractionDigits: 0 });
+	}
+
+	function time(iso) {
+		return new Date(iso).getTime();
+	}
+
+	function draw(data) {
+		var prices = data.prices;
+		function series(label, color, field) {
+			return {
+				label: label,
+				color: color,
+				points: prices.map(function (p) {
+					return [time(p.time), p[field]];
+				}),
+			};
+		}
+		// Same palette as the status cells: green for the best price, red for the worst.
+		MiniChart.line(document.getElementById("priceChart"), {
+			series: [
+				series("Min", "#28a745", "min"),
+				series("Median", "#007bff", "median"),
+				series("Max", "#dc3545", "max"),
+			],
+			format: money,
+			empty: "No available listings with a price in this period.",
+		});
+
+		var from = prices.length ? time(prices[0].time) : Date.now();
+		var to = prices.length ? time(prices[prices.length - 1].time) : Date.now();
+		MiniChart.timeline(document.getElementById("stockChart"), {
+			rows: data.in_stock
+				.filter(function (r) {
+					return r.intervals.length > 0;
+				})
+				.map(function (r) {
+					return {
+						label: r.retailer,
+						intervals: r.intervals.map(function (i) {
+							return [time(i.start), time(i.end), i.open];
+						}),
+					};
+				}),
+			from: from,
+			to: to,
+			empty: "No retailer had this model in stock in this period.",
+		});
+		status.textContent = prices.length + " scrapes recorded";
+	}
+
+	function load(since) {
+		status.textContent = "Loading…";
+		fetch("/gpu/" + model + "/history.json?since=" + encodeURIComponent(since))
+			.then(function (response) {
+				if (!response.ok) {
+					return response.text().then(function (body) {
+						throw new Error(body);
+					});
+				}
+				return response.json();
+			})
+			.then(draw)
+			.catch(function (err) {
+				status.textContent = "Failed to load history: " + err.message;
+			});
+	}
+
+	document.querySelectorAll('input[name="since"]').forEach(function (input) {
+		input.addEventListener("change", function () {
+			load(input.value);
+		});
+	});
+	load(document.querySelector('input[name="since"]:checked').value);
+})();
@@ -2,9 +2,9 @@ $(document).ready(function () {
 	// Initialize DataTable
 	var table = $('#gpuTable').DataTable({
 		pageLength: 25, // Default number of rows
-		order: [[2, 'asc']], // Default sort by price ascending
+		order: [[3, 'asc']], // Default sort by price ascending
 		columnDefs: [
-			{ targets: [4], orderable: false }, // Disable sorting for Link column
+			{ targets: [5], orderable: false }, // Disable sorting for Link column
 		],
 	})
 